@@ -2,7 +2,44 @@ use gim_config::config;
 use std::io::Result;
 use toml;
 
-/// Updates the AI configuration in the provided TOML value with the specified model, API key, URL, and language.
+use crate::cli::repo_config;
+
+/// The validated AI settings used to send chat requests.
+#[derive(Clone, Debug)]
+pub struct AiConfig {
+    /// The API url, empty to use the default url of the model.
+    pub url: String,
+    /// The model name.
+    pub model: String,
+    /// The API key.
+    pub apikey: String,
+    /// The answer language.
+    pub language: String,
+    /// The explicit provider, otherwise inferred from the model name.
+    pub provider: Option<String>,
+    /// Whether to stream the answers.
+    pub stream: bool,
+    /// Seconds to wait for the connection to the provider.
+    pub connect_timeout_secs: u64,
+    /// Seconds to wait for the provider to send data, before the answer starts and while it streams.
+    pub read_timeout_secs: u64,
+    /// How many times a failed request is retried.
+    pub max_retries: u32,
+    /// The profiles tried in order when the requests with this one fail.
+    pub fallbacks: Vec<AiConfig>,
+}
+
+impl AiConfig {
+    /// Returns this profile followed by its fallback profiles, in the order they are tried.
+    pub fn profiles(&self) -> Vec<&AiConfig> {
+        std::iter::once(self).chain(self.fallbacks.iter()).collect()
+    }
+}
+
+/// The key of the ai section listing the fallback profiles.
+pub const FALLBACK_KEY: &str = "fallback";
+
+/// Updates the AI configuration in the provided TOML value with the specified model, API key, URL, language and provider.
 ///
 /// # Arguments
 ///
@@ -11,12 +48,14 @@ use toml;
 /// * `apikey` - Optional API key to set.
 /// * `url` - Optional API URL to set.
 /// * `language` - Optional language to set.
+/// * `provider` - Optional provider to set.
 pub fn update_ai_config(
     config: &mut toml::Value,
     model: &Option<String>,
     apikey: &Option<String>,
     url: &Option<String>,
     language: &Option<String>,
+    provider: &Option<String>,
 ) {
     let ai_table = config
         .get_mut("ai")
@@ -45,36 +84,122 @@ pub fn update_ai_config(
             toml::Value::String(language_value.clone()),
         );
     }
+    if let Some(provider_value) = provider {
+        ai_table.insert(
+            "provider".to_string(),
+            toml::Value::String(provider_value.clone()),
+        );
+    }
 
     if let Err(e) = config::save_config(config) {
         eprintln!("Failed to save AI info to file: {}", e)
     }
 }
 
-/// Retrieves the AI configuration section from the TOML configuration file.
+/// Retrieves the effective AI configuration section, the repository configuration file
+/// merged over the global one.
 ///
 /// # Returns
 ///
 /// * `Ok(toml::Value)` containing the AI configuration if successful.
 /// * `Err(std::io::Error)` if the AI section is missing or invalid.
 pub fn get_ai_config() -> Result<toml::Value> {
-    let toml = config::get_config();
-    if toml.is_err() {
-        toml
-    } else if let Ok(toml) = toml {
-        let ai = toml.get("ai");
-        if let Some(ai) = ai {
-            Ok(ai.clone())
-        } else {
-            Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Failed to get ai section",
-            ))
-        }
+    let toml = repo_config::get_config()?;
+    if let Some(ai) = toml.get("ai") {
+        Ok(ai.clone())
     } else {
         Err(std::io::Error::new(
             std::io::ErrorKind::InvalidData,
-            "Failed to get ai config",
+            "Failed to get ai section",
         ))
     }
 }
+
+/// Reads the fallback profiles listed by the `[[ai.fallback]]` tables of the ai section.
+///
+/// A fallback profile sets its own `model`, `url`, `apikey` and `provider`, the other settings
+/// are inherited from the primary profile. Profiles without a model are reported and skipped.
+///
+/// # Arguments
+///
+/// * `ai_config` - The ai section of the configuration.
+/// * `primary` - The primary profile, read from the ai section itself.
+///
+/// # Returns
+///
+/// * `Vec<AiConfig>` in the order of the config file.
+pub fn get_fallback_configs(ai_config: &toml::Value, primary: &AiConfig) -> Vec<AiConfig> {
+    let Some(fallbacks) = ai_config.get(FALLBACK_KEY).and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+    let string = |table: &toml::Value, key: &str| {
+        table
+            .get(key)
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string())
+    };
+    fallbacks
+        .iter()
+        .enumerate()
+        .filter_map(|(index, table)| {
+            let Some(model) = string(table, "model").filter(|model| !model.is_empty()) else {
+                eprintln!(
+                    "Warning: Missing 'model' in fallback profile {} of AI config, skipped",
+                    index + 1
+                );
+                return None;
+            };
+            Some(AiConfig {
+                url: string(table, "url").unwrap_or_default(),
+                model,
+                apikey: string(table, "apikey").unwrap_or_default(),
+                provider: string(table, "provider"),
+                fallbacks: Vec::new(),
+                ..primary.clone()
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AiConfig, get_fallback_configs};
+
+    #[test]
+    fn test_get_fallback_configs() {
+        let ai_config: toml::Value = toml::from_str(
+            r#"
+            model = "qwen-turbo"
+            [[fallback]]
+            model = "gpt-4o-mini"
+            apikey = "sk-openai"
+            [[fallback]]
+            url = "http://localhost:11434"
+            [[fallback]]
+            model = "ollama/llama3.2"
+            "#,
+        )
+        .unwrap();
+        let primary = AiConfig {
+            url: String::new(),
+            model: "qwen-turbo".into(),
+            apikey: "sk-qwen".into(),
+            language: "Chinese".into(),
+            provider: Some("qwen".into()),
+            stream: false,
+            connect_timeout_secs: 5,
+            read_timeout_secs: 30,
+            max_retries: 1,
+            fallbacks: Vec::new(),
+        };
+        let fallbacks = get_fallback_configs(&ai_config, &primary);
+        assert_eq!(fallbacks.len(), 2);
+        assert_eq!(fallbacks[0].model, "gpt-4o-mini");
+        assert_eq!(fallbacks[0].apikey, "sk-openai");
+        assert_eq!(fallbacks[0].provider, None);
+        assert_eq!(fallbacks[0].language, "Chinese");
+        assert_eq!(fallbacks[0].read_timeout_secs, 30);
+        assert_eq!(fallbacks[1].model, "ollama/llama3.2");
+        assert!(fallbacks[1].apikey.is_empty());
+    }
+}
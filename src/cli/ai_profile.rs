@@ -0,0 +1,273 @@
+use gim_config::config;
+use std::error::Error;
+use toml::{Value, map::Map};
+
+/// The key of the ai section holding the named profiles, one table per profile.
+pub const PROFILES_KEY: &str = "profiles";
+/// The key of the ai section naming the profile used when none is given on the command line.
+pub const ACTIVE_PROFILE_KEY: &str = "profile";
+/// The profile name selecting the settings of the ai section itself.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// The settings that belong to a profile and are never inherited from the ai section,
+/// so that a profile never sends the changes to the model or the fallbacks of another one.
+pub const PROFILE_KEYS: [&str; 5] = ["model", "url", "apikey", "provider", "fallback"];
+
+/// Returns the ai section with the settings of the selected profile applied.
+///
+/// The profile given on the command line takes precedence over the active profile of the
+/// ai section. A profile sets its own model, url, api key, provider and fallbacks, the other
+/// settings, such as the language or the timeouts, are inherited from the ai section.
+///
+/// # Arguments
+///
+/// * `ai_config` - The ai section of the configuration.
+/// * `profile` - Optional profile name given on the command line.
+///
+/// # Returns
+///
+/// * `Ok(Value)` containing the effective ai section.
+/// * `Err(Box<dyn Error>)` if the selected profile doesn't exist.
+pub fn apply_profile(ai_config: &Value, profile: Option<&str>) -> Result<Value, Box<dyn Error>> {
+    let name = profile.or_else(|| ai_config.get(ACTIVE_PROFILE_KEY).and_then(|v| v.as_str()));
+    let mut effective = ai_config.clone();
+    let Some(table) = effective.as_table_mut() else {
+        return Ok(effective);
+    };
+    table.remove(PROFILES_KEY);
+    table.remove(ACTIVE_PROFILE_KEY);
+    let Some(name) = name.filter(|name| *name != DEFAULT_PROFILE) else {
+        return Ok(effective);
+    };
+
+    let Some(settings) = ai_config
+        .get(PROFILES_KEY)
+        .and_then(|v| v.get(name))
+        .and_then(|v| v.as_table())
+    else {
+        return Err(format!(
+            "unknown ai profile '{}', available profiles: {}",
+            name,
+            profile_names(ai_config).join(", ")
+        )
+        .into());
+    };
+    for key in PROFILE_KEYS {
+        table.remove(key);
+    }
+    // The url is optional in a profile, the default url of the model is used instead
+    table.insert("url".to_string(), Value::String(String::new()));
+    for (key, value) in settings {
+        table.insert(key.clone(), value.clone());
+    }
+    Ok(effective)
+}
+
+/// Adds a profile, or updates the given settings of an existing one.
+///
+/// # Arguments
+///
+/// * `config` - Mutable reference to the TOML configuration value.
+/// * `name` - The profile name.
+/// * `settings` - The settings to set, as key and optional value pairs.
+///
+/// # Returns
+///
+/// * `Ok(())` if the profile was saved.
+/// * `Err(Box<dyn Error>)` if the name is reserved, a new profile has no model or the config cannot be saved.
+pub fn add_profile(
+    config: &mut Value,
+    name: &str,
+    settings: &[(&str, &Option<String>)],
+) -> Result<(), Box<dyn Error>> {
+    if name == DEFAULT_PROFILE {
+        return Err(format!(
+            "'{}' is reserved for the settings of the ai section, use 'gim ai' to update them",
+            DEFAULT_PROFILE
+        )
+        .into());
+    }
+    let profiles = ai_table(config)?
+        .entry(PROFILES_KEY)
+        .or_insert_with(|| Value::Table(Map::new()))
+        .as_table_mut()
+        .ok_or("ai profiles is not a table")?;
+    let exists = profiles.contains_key(name);
+    let profile = profiles
+        .entry(name)
+        .or_insert_with(|| Value::Table(Map::new()))
+        .as_table_mut()
+        .ok_or_else(|| format!("ai profile '{}' is not a table", name))?;
+    for (key, value) in settings {
+        if let Some(value) = value {
+            profile.insert(key.to_string(), Value::String(value.clone()));
+        }
+    }
+    if !profile.contains_key("model") {
+        return Err("a new profile requires a model, use '--model <MODEL>'".into());
+    }
+
+    config::save_config(config)?;
+    println!(
+        "✅ {} ai profile '{}', use it with 'gim --profile {}' or 'gim ai profile use {}'",
+        if exists { "Updated" } else { "Added" },
+        name,
+        name,
+        name
+    );
+    Ok(())
+}
+
+/// Prints the profiles, marking the active one.
+///
+/// # Arguments
+///
+/// * `config` - Reference to the TOML configuration value.
+pub fn list_profiles(config: &Value) {
+    let Some(ai_config) = config.get("ai") else {
+        eprintln!("Error: ai section is not configured");
+        return;
+    };
+    let active = ai_config
+        .get(ACTIVE_PROFILE_KEY)
+        .and_then(|v| v.as_str())
+        .unwrap_or(DEFAULT_PROFILE);
+    let model = |table: &Value| {
+        table
+            .get("model")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let mark = |name: &str| if name == active { "*" } else { " " };
+    println!("{} {:<16}{}", mark(DEFAULT_PROFILE), DEFAULT_PROFILE, model(ai_config));
+    for name in profile_names(ai_config) {
+        let profile = &ai_config[PROFILES_KEY][name.as_str()];
+        println!("{} {:<16}{}", mark(&name), name, model(profile));
+    }
+}
+
+/// Makes a profile the one used when no profile is given on the command line.
+///
+/// # Arguments
+///
+/// * `config` - Mutable reference to the TOML configuration value.
+/// * `name` - The profile name, `default` to use the settings of the ai section.
+///
+/// # Returns
+///
+/// * `Ok(())` if the active profile was saved.
+/// * `Err(Box<dyn Error>)` if the profile doesn't exist or the config cannot be saved.
+pub fn use_profile(config: &mut Value, name: &str) -> Result<(), Box<dyn Error>> {
+    let ai_config = config.get("ai").ok_or("ai section is not configured")?;
+    apply_profile(ai_config, Some(name))?;
+
+    let table = ai_table(config)?;
+    if name == DEFAULT_PROFILE {
+        table.remove(ACTIVE_PROFILE_KEY);
+    } else {
+        table.insert(
+            ACTIVE_PROFILE_KEY.to_string(),
+            Value::String(name.to_string()),
+        );
+    }
+    config::save_config(config)?;
+    println!("✅ Using ai profile '{}'", name);
+    Ok(())
+}
+
+/// Removes a profile, the settings of the ai section are used again if it was active.
+///
+/// # Arguments
+///
+/// * `config` - Mutable reference to the TOML configuration value.
+/// * `name` - The profile name.
+///
+/// # Returns
+///
+/// * `Ok(())` if the profile was removed.
+/// * `Err(Box<dyn Error>)` if the profile doesn't exist or the config cannot be saved.
+pub fn remove_profile(config: &mut Value, name: &str) -> Result<(), Box<dyn Error>> {
+    let table = ai_table(config)?;
+    let Some(profiles) = table.get_mut(PROFILES_KEY).and_then(|v| v.as_table_mut()) else {
+        return Err(format!("unknown ai profile '{}'", name).into());
+    };
+    if profiles.remove(name).is_none() {
+        return Err(format!("unknown ai profile '{}'", name).into());
+    }
+    if profiles.is_empty() {
+        table.remove(PROFILES_KEY);
+    }
+    if table.get(ACTIVE_PROFILE_KEY).and_then(|v| v.as_str()) == Some(name) {
+        table.remove(ACTIVE_PROFILE_KEY);
+        println!("The removed profile was active, using the '{}' profile now", DEFAULT_PROFILE);
+    }
+    config::save_config(config)?;
+    println!("✅ Removed ai profile '{}'", name);
+    Ok(())
+}
+
+/// Returns the name of the active profile, if any.
+pub fn active_profile(ai_config: &Value) -> Option<&str> {
+    ai_config.get(ACTIVE_PROFILE_KEY).and_then(|v| v.as_str())
+}
+
+fn profile_names(ai_config: &Value) -> Vec<String> {
+    ai_config
+        .get(PROFILES_KEY)
+        .and_then(|v| v.as_table())
+        .map(|profiles| profiles.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+fn ai_table(config: &mut Value) -> Result<&mut Map<String, Value>, Box<dyn Error>> {
+    config
+        .get_mut("ai")
+        .and_then(|v| v.as_table_mut())
+        .ok_or_else(|| "ai section is not configured".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply_profile;
+
+    #[test]
+    fn test_apply_profile() {
+        let ai_config: toml::Value = toml::from_str(
+            r#"
+            model = "gpt-4o-mini"
+            url = "https://api.openai.com"
+            apikey = "sk-openai"
+            language = "Chinese"
+            profile = "cloud"
+            [[fallback]]
+            model = "deepseek-chat"
+            [profiles.local]
+            model = "ollama/llama3.2"
+            [profiles.cloud]
+            model = "claude-sonnet-4-0"
+            apikey = "sk-ant"
+            "#,
+        )
+        .unwrap();
+
+        let effective = apply_profile(&ai_config, None).unwrap();
+        assert_eq!(effective["model"].as_str(), Some("claude-sonnet-4-0"));
+        assert_eq!(effective["url"].as_str(), Some(""));
+        assert_eq!(effective["language"].as_str(), Some("Chinese"));
+        assert!(effective.get("fallback").is_none());
+        assert!(effective.get("profiles").is_none());
+
+        let effective = apply_profile(&ai_config, Some("local")).unwrap();
+        assert_eq!(effective["model"].as_str(), Some("ollama/llama3.2"));
+        assert!(effective.get("apikey").is_none());
+
+        let effective = apply_profile(&ai_config, Some("default")).unwrap();
+        assert_eq!(effective["model"].as_str(), Some("gpt-4o-mini"));
+        assert!(effective.get("fallback").is_some());
+
+        let error = apply_profile(&ai_config, Some("missing")).unwrap_err();
+        assert!(error.to_string().contains("local"));
+    }
+}
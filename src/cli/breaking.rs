@@ -0,0 +1,292 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fmt;
+
+use crate::cli::{budget, diff, verbose::print_verbose};
+
+lazy_static! {
+    // `pub(crate)` and the like are left out, they are not part of the public API
+    static ref RUST_ITEM: Regex = Regex::new(
+        r#"^\s*pub\s+(?:(?:async|const|unsafe|extern(?:\s+"[^"]*")?)\s+)*(fn|struct|enum|trait|type|mod|const|static|union)\s+([A-Za-z_]\w*)"#
+    )
+    .unwrap();
+    static ref TS_ITEM: Regex = Regex::new(
+        r"^\s*export\s+(?:default\s+)?(?:declare\s+)?(?:abstract\s+)?(?:async\s+)?(function\*?|class|interface|type|const|let|var|enum)\s*([A-Za-z_$][\w$]*)"
+    )
+    .unwrap();
+    // The calls defining flags, such as `.option("-f, --force")` or `add_argument('--force')`,
+    // other strings such as the arguments of a command are not flags of the program
+    static ref FLAG_DEFINITION: Regex = Regex::new(
+        r"(?:\.(?:option|requiredOption)|\badd_argument|\badd_option)\(([^)]*)"
+    )
+    .unwrap();
+    static ref FLAG_NAME: Regex = Regex::new(r#"["',\s](--[A-Za-z][\w-]*)"#).unwrap();
+    // The long flags of the clap builder, `.long("force")`
+    static ref CLAP_LONG_CALL: Regex = Regex::new(r#"\.long\(\s*"([^"]+)"\s*\)"#).unwrap();
+    // The long flags of clap, `#[arg(long)]` followed by the field
+    static ref CLAP_ATTRIBUTE: Regex = Regex::new(r"^\s*#\[(?:arg|clap|structopt)\(.*\blong\b").unwrap();
+    static ref CLAP_LONG_NAME: Regex = Regex::new(r#"\blong\s*=\s*"([^"]+)""#).unwrap();
+    static ref CLAP_FIELD: Regex = Regex::new(r"^\s*(?:pub(?:\([^)]*\))?\s+)?([a-z_][a-z0-9_]*)\s*:").unwrap();
+}
+
+const RUST_EXTENSIONS: [&str; 1] = [".rs"];
+const TS_EXTENSIONS: [&str; 8] = [".ts", ".tsx", ".mts", ".cts", ".js", ".jsx", ".mjs", ".cjs"];
+/// Files whose text is not code, the flags they mention are not removed from any program.
+const TEXT_EXTENSIONS: [&str; 5] = [".md", ".txt", ".rst", ".adoc", ".html"];
+/// Paths of tests and examples, which are not part of the public API.
+const NON_API_PATHS: [&str; 5] = ["tests/", "benches/", "examples/", ".test.", ".spec."];
+
+/// A public item found in the changed lines.
+#[derive(Debug, PartialEq)]
+struct Item {
+    /// The kind of item, such as `pub fn` or `export function`, `flag` for a CLI flag.
+    kind: String,
+    name: String,
+    /// The first line of the declaration, whitespace collapsed.
+    signature: String,
+    path: String,
+}
+
+/// A change of the public API which may break its users.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    /// The item, such as `pub fn parse` or the `--force` flag.
+    pub item: String,
+    /// The file the item was in.
+    pub path: String,
+    /// Whether the item was removed, otherwise its signature changed.
+    pub removed: bool,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.removed {
+            write!(f, "removed {} from {}", self.item, self.path)
+        } else {
+            write!(f, "changed the signature of {} in {}", self.item, self.path)
+        }
+    }
+}
+
+/// Looks for removed or changed public items in the staged changes, and in the last commit
+/// when amending: Rust `pub` items, exported TypeScript and JavaScript items, and CLI flags.
+///
+/// It is a heuristic over the changed lines: only the first line of a declaration is compared,
+/// and an item removed from a file but added to another one is taken for moved.
+///
+/// # Arguments
+///
+/// * `amend` - Whether the changes of the last commit are described.
+///
+/// # Returns
+///
+/// * `Vec<Finding>` containing the breaking changes, empty if none is found.
+pub fn detect(amend: bool) -> Vec<Finding> {
+    let findings = analyze(&diff::complete_diff(amend));
+    if !findings.is_empty() {
+        print_verbose(&format!("Breaking changes found: {:?}", findings));
+    }
+    findings
+}
+
+/// Returns the breaking change footer describing the findings.
+pub fn footer(findings: &[Finding]) -> String {
+    let descriptions: Vec<String> = findings.iter().map(|finding| finding.to_string()).collect();
+    format!("BREAKING CHANGE: {}", descriptions.join("; "))
+}
+
+fn analyze(full_diff: &str) -> Vec<Finding> {
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for file in budget::split_files(full_diff) {
+        if NON_API_PATHS.iter().any(|part| file.path.contains(part)) {
+            continue;
+        }
+        removed.extend(items(file.path, &changed_lines(file.text, '-')));
+        added.extend(items(file.path, &changed_lines(file.text, '+')));
+    }
+
+    let mut findings: Vec<Finding> = Vec::new();
+    for item in &removed {
+        let same_name: Vec<&Item> = added
+            .iter()
+            .filter(|other| other.kind == item.kind && other.name == item.name)
+            .collect();
+        let kept = same_name
+            .iter()
+            .any(|other| other.signature == item.signature);
+        if kept || (item.kind == "flag" && !same_name.is_empty()) {
+            continue;
+        }
+        let finding = Finding {
+            item: if item.kind == "flag" {
+                format!("the `{}` flag", item.name)
+            } else {
+                format!("`{} {}`", item.kind, item.name)
+            },
+            path: item.path.clone(),
+            removed: same_name.is_empty(),
+        };
+        if !findings.iter().any(|other| other.item == finding.item) {
+            findings.push(finding);
+        }
+    }
+    findings
+}
+
+/// Returns the lines of one side of a file diff, without their prefix. The other lines are
+/// empty, so that context lines end a run of changed lines.
+///
+/// The `diff --git`, `---` and `+++` header lines come before the first hunk and are left out,
+/// a changed line starting with `--` or `++` is kept.
+fn changed_lines(text: &str, prefix: char) -> Vec<&str> {
+    text.lines()
+        .skip_while(|line| !line.starts_with("@@"))
+        .map(|line| line.strip_prefix(prefix).unwrap_or(""))
+        .collect()
+}
+
+/// Finds the public items declared in the lines of one side of a file diff. Lines which are
+/// not on this side are empty.
+fn items(path: &str, lines: &[&str]) -> Vec<Item> {
+    let is_rust = RUST_EXTENSIONS.iter().any(|ext| path.ends_with(ext));
+    let is_ts = TS_EXTENSIONS.iter().any(|ext| path.ends_with(ext));
+    let is_text = TEXT_EXTENSIONS.iter().any(|ext| path.ends_with(ext));
+    let mut items = Vec::new();
+    let mut item = |kind: &str, name: &str, line: &str| {
+        items.push(Item {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            signature: line.split_whitespace().collect::<Vec<_>>().join(" "),
+            path: path.to_string(),
+        })
+    };
+    // The long name of a clap attribute, waiting for its field
+    let mut clap_flag: Option<Option<String>> = None;
+    for line in lines {
+        let declaration = if is_rust {
+            RUST_ITEM
+                .captures(line)
+                .map(|c| (format!("pub {}", &c[1]), c[2].to_string()))
+        } else if is_ts {
+            TS_ITEM
+                .captures(line)
+                .map(|c| (format!("export {}", &c[1]), c[2].to_string()))
+        } else {
+            None
+        };
+        if let Some((kind, name)) = declaration {
+            let signature = line.trim().trim_end_matches(['{', ';']).trim_end();
+            item(&kind, &name, signature);
+        }
+        if !is_text {
+            for definition in FLAG_DEFINITION.captures_iter(line) {
+                for flag in FLAG_NAME.captures_iter(&definition[1]) {
+                    item("flag", &flag[1], &flag[1]);
+                }
+            }
+        }
+        if !is_rust {
+            continue;
+        }
+        for long in CLAP_LONG_CALL.captures_iter(line) {
+            let flag = format!("--{}", &long[1]);
+            item("flag", &flag, &flag);
+        }
+        if CLAP_ATTRIBUTE.is_match(line) {
+            clap_flag = Some(CLAP_LONG_NAME.captures(line).map(|c| c[1].to_string()));
+        } else if let Some(long) = clap_flag.take() {
+            if let Some(field) = CLAP_FIELD.captures(line) {
+                let flag = format!("--{}", long.unwrap_or_else(|| field[1].replace('_', "-")));
+                item("flag", &flag, &flag);
+            } else if line.trim_start().starts_with("#[") || line.trim_start().starts_with("///") {
+                // Other attributes and docs may come between the attribute and the field
+                clap_flag = Some(long);
+            }
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Finding, analyze, changed_lines, footer};
+
+    #[test]
+    fn test_analyze() {
+        let full_diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,9 +1,7 @@
+-pub fn parse(input: &str) -> Ast {
++pub fn parse(input: &str, strict: bool) -> Ast {
+-pub fn tokenize(input: &str) -> Vec<Token> {
+-pub(crate) fn helper() {}
+-pub fn moved() {}
+ struct Cli {
+-    /// Show verbose output
+-    #[arg(short, long)]
+-    pub verbose: bool,
+ }
+diff --git a/src/other.rs b/src/other.rs
+--- a/src/other.rs
++++ b/src/other.rs
+@@ -1,1 +1,2 @@
++pub fn moved() {}
+diff --git a/web/api.ts b/web/api.ts
+--- a/web/api.ts
++++ b/web/api.ts
+@@ -1,2 +1,1 @@
+-export async function fetchUser(id: string) {
+-program.option('--force')
++program.option('--force', 'Force it')
+-program.option('-d, --dry-run')
+diff --git a/src/git.rs b/src/git.rs
+--- a/src/git.rs
++++ b/src/git.rs
+@@ -1,2 +1,1 @@
+-        Command::new(\"git\").args([\"diff\", \"--cached\"])
+-        Arg::new(\"color\").long(\"color\")
++        Arg::new(\"color\").long(\"colour\")
+diff --git a/tests/cli.rs b/tests/cli.rs
+--- a/tests/cli.rs
++++ b/tests/cli.rs
+@@ -1,1 +0,0 @@
+-pub fn fixture() {}
+";
+        let findings = analyze(full_diff);
+        let descriptions: Vec<String> = findings.iter().map(Finding::to_string).collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                "changed the signature of `pub fn parse` in src/lib.rs",
+                "removed `pub fn tokenize` from src/lib.rs",
+                "removed the `--verbose` flag from src/lib.rs",
+                "removed `export function fetchUser` from web/api.ts",
+                "removed the `--dry-run` flag from web/api.ts",
+                "removed the `--color` flag from src/git.rs",
+            ]
+        );
+        assert!(analyze("").is_empty());
+        assert_eq!(
+            footer(&findings[1..2]),
+            "BREAKING CHANGE: removed `pub fn tokenize` from src/lib.rs"
+        );
+    }
+
+    #[test]
+    fn test_changed_lines() {
+        let text = "\
+diff --git a/schema.sql b/schema.sql
+--- a/schema.sql
++++ b/schema.sql
+@@ -1,3 +1,3 @@
+---- the users
++++counter;
+ SELECT 1;
+";
+        assert_eq!(changed_lines(text, '-'), vec!["", "--- the users", "", ""]);
+        assert_eq!(changed_lines(text, '+'), vec!["", "", "++counter;", ""]);
+    }
+}
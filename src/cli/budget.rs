@@ -0,0 +1,277 @@
+/// Lock files and generated files, the first ones left out when the changes are over the budget.
+const GENERATED_FILE_NAMES: [&str; 12] = [
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "bun.lockb",
+    "poetry.lock",
+    "Pipfile.lock",
+    "Gemfile.lock",
+    "composer.lock",
+    "go.sum",
+    "flake.lock",
+];
+/// Minified files, whose content is also replaced by a placeholder in the diffs.
+pub const MINIFIED_SUFFIXES: [&str; 2] = [".min.js", ".min.css"];
+const GENERATED_FILE_SUFFIXES: [&str; 5] = [
+    ".map",
+    ".snap",
+    ".pb.go",
+    "_pb2.py",
+    ".g.dart",
+];
+/// Output directories, only at the root of the repository: `src/build/` is usually written by
+/// hand. Other generated files can be marked `linguist-generated` in `.gitattributes`.
+const GENERATED_DIRS: [&str; 4] = ["dist/", "build/", "generated/", "vendor/"];
+
+/// The smallest budget of a truncated file, enough for its header and the beginning of its hunks.
+const MIN_FILE_TOKENS: usize = 200;
+
+/// The diff of a file.
+#[derive(Debug)]
+pub struct FileDiff<'a> {
+    /// The path of the file, the new one when it was renamed.
+    pub path: &'a str,
+    /// The diff of the file, from its `diff --git` line.
+    pub text: &'a str,
+}
+
+/// A diff fitted into a token budget.
+#[derive(Debug, Default, PartialEq)]
+pub struct FittedDiff {
+    /// The diff sent to the AI.
+    pub diff: String,
+    /// The files whose diff was truncated.
+    pub truncated: Vec<String>,
+    /// The files left out of the diff, to be summarized by `git diff --stat`.
+    pub summarized: Vec<String>,
+}
+
+/// Estimates the count of tokens of a text.
+///
+/// About 4 ASCII characters make a token, while other characters, such as CJK ones,
+/// are usually a token each.
+pub fn estimate_tokens(text: &str) -> usize {
+    let ascii = text.bytes().filter(u8::is_ascii).count();
+    let others = text.chars().filter(|c| !c.is_ascii()).count();
+    ascii.div_ceil(4) + others
+}
+
+/// Checks whether a file is a lock file or a generated file.
+pub fn is_generated(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    GENERATED_FILE_NAMES.contains(&name)
+        || MINIFIED_SUFFIXES
+            .iter()
+            .chain(&GENERATED_FILE_SUFFIXES)
+            .any(|suffix| name.ends_with(suffix))
+        || GENERATED_DIRS.iter().any(|dir| path.starts_with(dir))
+}
+
+/// Fits the output of `git diff` into a token budget.
+///
+/// Diffs within the budget are kept as they are. Otherwise lock files and generated files are
+/// left out first, then the diffs of the largest files are truncated, and finally the files that
+/// still don't fit are left out, to be summarized by `git diff --stat` instead.
+///
+/// # Arguments
+///
+/// * `full_diff` - The output of `git diff`.
+/// * `budget` - The count of tokens the diff may use.
+///
+/// # Returns
+///
+/// * `FittedDiff` containing the diff within the budget and the files that were cut.
+pub fn fit_diff(full_diff: &str, budget: usize) -> FittedDiff {
+    if estimate_tokens(full_diff) <= budget {
+        return FittedDiff {
+            diff: full_diff.to_string(),
+            ..Default::default()
+        };
+    }
+
+    let mut fitted = FittedDiff::default();
+    let mut files: Vec<FileDiff> = Vec::new();
+    for file in split_files(full_diff) {
+        if is_generated(file.path) {
+            fitted.summarized.push(file.path.to_string());
+        } else {
+            files.push(file);
+        }
+    }
+
+    // Share the budget between the files: the small ones keep their whole diff, the
+    // remaining budget is split evenly between the large ones
+    let mut sizes: Vec<usize> = files.iter().map(|f| estimate_tokens(f.text)).collect();
+    let mut remaining = budget;
+    let mut large: Vec<usize> = (0..files.len()).collect();
+    large.sort_by_key(|&i| sizes[i]);
+    let mut cap = usize::MAX;
+    while let Some(&smallest) = large.first() {
+        let share = remaining / large.len();
+        if sizes[smallest] > share {
+            cap = share.max(MIN_FILE_TOKENS);
+            break;
+        }
+        remaining -= sizes[smallest];
+        large.remove(0);
+    }
+
+    let mut used = 0;
+    for (i, file) in files.iter().enumerate() {
+        let text = if sizes[i] > cap {
+            fitted.truncated.push(file.path.to_string());
+            let text = truncate(file.text, cap);
+            sizes[i] = estimate_tokens(&text);
+            text
+        } else {
+            file.text.to_string()
+        };
+        if used + sizes[i] > budget {
+            fitted.summarized.push(file.path.to_string());
+            fitted.truncated.retain(|path| path != file.path);
+            continue;
+        }
+        used += sizes[i];
+        fitted.diff.push_str(&text);
+    }
+    fitted
+}
+
+/// Splits the output of `git diff` into the diffs of the files.
+pub fn split_files(full_diff: &str) -> Vec<FileDiff<'_>> {
+    let mut starts: Vec<usize> = full_diff
+        .match_indices("diff --git ")
+        .filter(|(pos, _)| *pos == 0 || full_diff.as_bytes()[pos - 1] == b'\n')
+        .map(|(pos, _)| pos)
+        .collect();
+    starts.push(full_diff.len());
+    starts
+        .windows(2)
+        .map(|range| {
+            let text = &full_diff[range[0]..range[1]];
+            let header = text.lines().next().unwrap_or_default();
+            let path = header
+                .rsplit_once(" b/")
+                .map(|(_, path)| path)
+                .unwrap_or(header);
+            FileDiff { path, text }
+        })
+        .collect()
+}
+
+/// Groups consecutive items into chunks within the budget.
+///
+/// The items are kept in order, so that the files of a directory, listed together by git,
+/// mostly end in the same chunk. An item larger than the budget gets a chunk of its own.
+///
+/// # Arguments
+///
+/// * `sizes` - The count of tokens of each item.
+/// * `budget` - The count of tokens a chunk may use.
+///
+/// # Returns
+///
+/// * `Vec<Vec<usize>>` containing the indexes of the items of each chunk.
+pub fn chunk(sizes: &[usize], budget: usize) -> Vec<Vec<usize>> {
+    let mut chunks: Vec<Vec<usize>> = Vec::new();
+    let mut used = 0;
+    for (i, size) in sizes.iter().enumerate() {
+        match chunks.last_mut() {
+            Some(current) if used + size <= budget => current.push(i),
+            _ => {
+                chunks.push(vec![i]);
+                used = 0;
+            }
+        }
+        used += size;
+    }
+    chunks
+}
+
+/// Keeps the beginning of a file diff within the budget, whole lines only.
+fn truncate(text: &str, budget: usize) -> String {
+    // Leave room for the truncation note
+    let budget = budget.saturating_sub(10);
+    let mut kept = String::new();
+    let mut used = 0;
+    let mut lines = text.lines();
+    for line in lines.by_ref() {
+        let tokens = estimate_tokens(line) + 1;
+        if used + tokens > budget {
+            break;
+        }
+        used += tokens;
+        kept.push_str(line);
+        kept.push('\n');
+    }
+    let left = lines.count() + 1;
+    kept.push_str(&format!("... ({} more lines truncated)\n", left));
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk, estimate_tokens, fit_diff, is_generated};
+
+    fn file_diff(path: &str, lines: usize) -> String {
+        let mut diff = format!(
+            "diff --git a/{path} b/{path}\nindex 1..2 100644\n--- a/{path}\n+++ b/{path}\n@@ -1 +1,{lines} @@\n"
+        );
+        for i in 0..lines {
+            diff.push_str(&format!("+line number {} of the file\n", i));
+        }
+        diff
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens("abcdefgh"), 2);
+        assert_eq!(estimate_tokens("提交信息"), 4);
+    }
+
+    #[test]
+    fn test_is_generated() {
+        assert!(is_generated("Cargo.lock"));
+        assert!(is_generated("web/package-lock.json"));
+        assert!(is_generated("static/app.min.js"));
+        assert!(is_generated("dist/index.js"));
+        assert!(!is_generated("src/distance.rs"));
+        assert!(!is_generated("src/build/mod.rs"));
+        assert!(!is_generated("crates/foo/vendor/lib.rs"));
+    }
+
+    #[test]
+    fn test_fit_diff() {
+        let small = format!("{}{}", file_diff("src/a.rs", 3), file_diff("Cargo.lock", 3));
+        assert_eq!(fit_diff(&small, 10_000).diff, small);
+
+        // The lock file is left out first, then the large file is truncated
+        let full = format!(
+            "{}{}{}",
+            file_diff("src/a.rs", 5),
+            file_diff("Cargo.lock", 500),
+            file_diff("src/big.rs", 500)
+        );
+        let fitted = fit_diff(&full, 1_000);
+        assert_eq!(fitted.summarized, vec!["Cargo.lock"]);
+        assert_eq!(fitted.truncated, vec!["src/big.rs"]);
+        assert!(fitted.diff.contains(&file_diff("src/a.rs", 5)));
+        assert!(fitted.diff.contains("more lines truncated"));
+        assert!(estimate_tokens(&fitted.diff) <= 1_000);
+
+        // Files that still don't fit are left out
+        let many: String = (0..20).map(|i| file_diff(&format!("src/f{}.rs", i), 50)).collect();
+        let fitted = fit_diff(&many, 1_000);
+        assert!(!fitted.summarized.is_empty());
+        assert!(estimate_tokens(&fitted.diff) <= 1_000);
+    }
+
+    #[test]
+    fn test_chunk() {
+        assert_eq!(chunk(&[10, 20, 30, 500, 5], 60), vec![vec![0, 1, 2], vec![3], vec![4]]);
+        assert_eq!(chunk(&[], 60), Vec::<Vec<usize>>::new());
+    }
+}
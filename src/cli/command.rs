@@ -1,5 +1,7 @@
 use clap::{Parser, Subcommand};
 
+use crate::cli::{diff::DiffStrategy, provider::registry};
+
 /// Command-line interface structure for the gim tool, using clap for argument parsing.
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -22,6 +24,38 @@ pub struct GimCli {
     /// Show verbose output
     #[arg(short, long, default_value_t = false)]
     pub verbose: bool,
+
+    /// Commit the generated message without reviewing it
+    #[arg(short, long, default_value_t = false)]
+    pub yes: bool,
+
+    /// Print the generated message without staging or committing anything
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
+
+    /// Use the given ai profile for this run instead of the active one
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Add a Signed-off-by trailer with the committer identity
+    #[arg(short, long, default_value_t = false)]
+    pub signoff: bool,
+
+    /// Add a Co-authored-by trailer, such as "Name <email>". Can be repeated
+    #[arg(long, value_name = "AUTHOR")]
+    pub co_author: Vec<String>,
+
+    /// Add a Reviewed-by trailer, such as "Name <email>". Can be repeated
+    #[arg(long, value_name = "REVIEWER")]
+    pub reviewed_by: Vec<String>,
+
+    /// Add a BREAKING CHANGE footer with the given description
+    #[arg(long, value_name = "DESCRIPTION")]
+    pub breaking: Option<String>,
+
+    /// Add a trailer, such as "Acked-by: Name <email>". Can be repeated
+    #[arg(long, value_name = "TOKEN: VALUE")]
+    pub trailer: Vec<String>,
 }
 
 /// Enum representing all supported subcommands for the gim CLI.
@@ -59,6 +93,10 @@ pub enum GimCommands {
         /// Optional: Reset the prompt to default
         #[arg(long, default_value_t = false)]
         reset: bool,
+
+        /// Optional: Show the prompts rendered against the current repository
+        #[arg(short, long, default_value_t = false)]
+        render: bool,
     },
 
     /// Setup the ai-api configuration
@@ -78,16 +116,127 @@ pub enum GimCommands {
         /// the answer language
         #[arg(short, long)]
         language: Option<String>,
+
+        #[arg(short, long, help = registry::provider_help())]
+        provider: Option<String>,
+
+        #[command(subcommand)]
+        action: Option<AiAction>,
     },
 
     /// Setup the git configuration
     Config {
-        /// Git commit changed lines limit
+        /// Stop when the changed lines count exceeds this limit, 0 to never stop
         #[arg(long)]
         lines_limit: Option<usize>,
 
+        /// The count of tokens the changes sent to the AI may use
+        #[arg(long)]
+        token_budget: Option<usize>,
+
+        /// How the changes are sent to the AI: 'single' request, 'chunked' to summarize
+        /// large changes in concurrent chunks, or 'per-file' to summarize every file on its own
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(DiffStrategy::NAMES))]
+        diff_strategy: Option<String>,
+
+        /// The count of AI requests sent at the same time when summarizing chunks or files
+        #[arg(long)]
+        concurrency: Option<usize>,
+
+        /// How many times the AI is asked for a subject following Conventional Commits,
+        /// 0 to accept any subject
+        #[arg(long)]
+        subject_attempts: Option<usize>,
+
         /// Print config file's location
         #[arg(long, default_value_t = false)]
         show_location: bool,
     },
+
+    /// Manage the prepare-commit-msg git hook, which pre-fills the message of 'git commit'
+    Hook {
+        #[command(subcommand)]
+        action: HookAction,
+    },
+}
+
+/// Enum representing the actions of the `ai` subcommand.
+#[derive(Subcommand)]
+pub enum AiAction {
+    /// Manage named ai profiles, switchable per run with '--profile <name>'
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+}
+
+/// Enum representing the actions of the `ai profile` subcommand.
+#[derive(Subcommand)]
+pub enum ProfileAction {
+    /// Add a profile, or update the given settings of an existing one
+    Add {
+        /// the profile name
+        name: String,
+
+        /// the ai model name
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// the ai api key
+        #[arg(short = 'k', long)]
+        apikey: Option<String>,
+
+        /// the ai api url
+        #[arg(short, long)]
+        url: Option<String>,
+
+        /// the answer language, inherited from the ai section when not set
+        #[arg(short, long)]
+        language: Option<String>,
+
+        #[arg(short, long, help = registry::provider_help())]
+        provider: Option<String>,
+    },
+
+    /// List the profiles, the active one is marked with '*'
+    List,
+
+    /// Use a profile when '--profile' is not given, 'default' for the ai section settings
+    Use {
+        /// the profile name
+        name: String,
+    },
+
+    /// Remove a profile
+    Remove {
+        /// the profile name
+        name: String,
+    },
+}
+
+/// Enum representing the actions of the `hook` subcommand.
+#[derive(Subcommand)]
+pub enum HookAction {
+    /// Install the hook into the current repository
+    Install {
+        /// Overwrite an existing prepare-commit-msg hook
+        #[arg(short, long, default_value_t = false)]
+        force: bool,
+    },
+
+    /// Remove the hook installed by gim from the current repository
+    Uninstall,
+
+    /// Run the hook, called by git with the commit message file
+    #[command(hide = true)]
+    Run {
+        /// The commit message file
+        message_file: String,
+
+        /// The source of the commit message
+        source: Option<String>,
+
+        /// The commit object name, when amending or reusing a commit
+        sha: Option<String>,
+    },
 }
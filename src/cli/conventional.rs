@@ -0,0 +1,449 @@
+use std::fmt;
+
+/// The commit types of Conventional Commits, as accepted by `@commitlint/config-conventional`.
+pub const DEFAULT_TYPES: [&str; 11] = [
+    "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert",
+];
+/// The default maximal length of a subject, in characters, the one asked by the default
+/// subject prompt.
+pub const DEFAULT_MAX_LENGTH: usize = 50;
+
+/// Labels the AI may put before the subject.
+const SUBJECT_LABELS: [&str; 3] = ["commit message:", "commit subject:", "subject:"];
+
+/// The cases a subject description can be checked against, named as by commitlint.
+pub const CASES: [&str; 8] = [
+    "lower-case",
+    "upper-case",
+    "camel-case",
+    "kebab-case",
+    "pascal-case",
+    "sentence-case",
+    "snake-case",
+    "start-case",
+];
+
+/// The rules a commit message is checked against.
+#[derive(Clone, Debug)]
+pub struct SubjectRules {
+    /// The allowed commit types.
+    pub types: Vec<String>,
+    /// The maximal length of the subject, in characters.
+    pub max_length: usize,
+    /// The allowed scopes, any scope when empty.
+    pub scopes: Vec<String>,
+    /// Whether the subject must have a scope.
+    pub scope_required: bool,
+    /// Whether the subject must not have a scope.
+    pub scope_forbidden: bool,
+    /// The cases the description must, or must not, be in.
+    pub subject_case: Option<CaseRule>,
+    /// The maximal length of the lines of the body, in characters.
+    pub body_max_line_length: Option<usize>,
+}
+
+/// A rule on the case of the description.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaseRule {
+    /// `true` when the description must be in one of the cases, `false` when it must be in none.
+    pub always: bool,
+    /// The names of the cases, see [`CASES`].
+    pub cases: Vec<String>,
+}
+
+impl Default for SubjectRules {
+    fn default() -> Self {
+        SubjectRules {
+            types: DEFAULT_TYPES.iter().map(|t| t.to_string()).collect(),
+            max_length: DEFAULT_MAX_LENGTH,
+            scopes: Vec::new(),
+            scope_required: false,
+            scope_forbidden: false,
+            subject_case: None,
+            body_max_line_length: None,
+        }
+    }
+}
+
+impl SubjectRules {
+    /// Describes the rules, to be added to the subject prompt.
+    pub fn describe(&self) -> String {
+        let mut rules = vec![format!(
+            "Follow these commit message rules strictly:\n- The format is 'type(scope): description', the type is one of: {}",
+            self.types.join(", ")
+        )];
+        if self.scope_forbidden {
+            rules.push("- Do not add any scope".to_string());
+        } else if !self.scopes.is_empty() {
+            rules.push(format!(
+                "- The scope is {}one of: {}",
+                if self.scope_required { "required and " } else { "" },
+                self.scopes.join(", ")
+            ));
+        } else if self.scope_required {
+            rules.push("- The scope is required".to_string());
+        }
+        if let Some(case) = &self.subject_case {
+            rules.push(format!(
+                "- The description {} {}",
+                if case.always { "must be in" } else { "must not be in" },
+                case.cases.join(" or ")
+            ));
+        }
+        rules.push(format!(
+            "- The whole subject is at most {} characters long",
+            self.max_length
+        ));
+        rules.join("\n")
+    }
+}
+
+/// A commit subject following Conventional Commits: `type(scope)!: description`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subject {
+    /// The commit type, such as `feat`.
+    pub kind: String,
+    /// The optional scope, such as `parser`.
+    pub scope: Option<String>,
+    /// Whether the `!` breaking change marker is set.
+    pub breaking: bool,
+    /// The description after the colon.
+    pub description: String,
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(scope) = &self.scope {
+            write!(f, "({})", scope)?;
+        }
+        if self.breaking {
+            write!(f, "!")?;
+        }
+        write!(f, ": {}", self.description)
+    }
+}
+
+/// Repairs what the AI commonly adds around a subject: code fences, markdown, quotes, labels
+/// such as `Subject:`, an upper case type, a missing space after the colon and a final period.
+///
+/// # Arguments
+///
+/// * `answer` - The answer of the AI.
+/// * `rules` - The rules giving the allowed commit types.
+///
+/// # Returns
+///
+/// * `String` containing the repaired subject, its first meaningful line only.
+pub fn repair(answer: &str, rules: &SubjectRules) -> String {
+    let line = answer
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("```"))
+        .unwrap_or_default();
+    let mut subject = line.to_string();
+    // Strip the wrapping markers until none is left, such as **"feat: x"**
+    loop {
+        // Headings, quotes and list items
+        let trimmed = subject.trim().trim_start_matches(['#', '>']).trim_start();
+        let trimmed = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+            .unwrap_or(trimmed)
+            .trim();
+        let trimmed = SUBJECT_LABELS
+            .iter()
+            .find_map(|label| {
+                trimmed
+                    .get(..label.len())
+                    .filter(|prefix| prefix.eq_ignore_ascii_case(label))
+                    .map(|_| trimmed[label.len()..].trim())
+            })
+            .unwrap_or(trimmed);
+        let trimmed = strip_pair(trimmed, "**")
+            .or_else(|| strip_pair(trimmed, "__"))
+            .or_else(|| strip_pair(trimmed, "`"))
+            .or_else(|| strip_pair(trimmed, "\""))
+            .or_else(|| strip_pair(trimmed, "'"))
+            .unwrap_or(trimmed)
+            .trim()
+            .to_string();
+        if trimmed == subject {
+            break;
+        }
+        subject = trimmed;
+    }
+    let subject = subject.trim_end_matches('.').trim_end().to_string();
+
+    // Normalize the type part, when it is one of the allowed types
+    let Some((head, description)) = subject.split_once(':') else {
+        return subject;
+    };
+    let (kind, rest) = match head.find(['(', '!']) {
+        Some(index) => head.split_at(index),
+        None => (head, ""),
+    };
+    let kind = kind.to_lowercase();
+    if !rules.types.contains(&kind) {
+        return subject;
+    }
+    format!("{}{}: {}", kind, rest, description.trim())
+}
+
+fn strip_pair<'a>(text: &'a str, marker: &str) -> Option<&'a str> {
+    text.strip_prefix(marker)?.strip_suffix(marker)
+}
+
+/// Parses a subject and checks it against the rules.
+///
+/// # Arguments
+///
+/// * `subject` - The commit subject.
+/// * `rules` - The rules the subject is checked against.
+///
+/// # Returns
+///
+/// * `Ok(Subject)` containing the parts of the subject.
+/// * `Err(String)` describing why the subject is invalid, to be sent back to the AI.
+pub fn validate(subject: &str, rules: &SubjectRules) -> Result<Subject, String> {
+    let format = "the subject must follow the format 'type(scope): description', the scope is optional";
+    let (head, description) = subject.split_once(": ").ok_or(format)?;
+    let (head, breaking) = match head.strip_suffix('!') {
+        Some(head) => (head, true),
+        None => (head, false),
+    };
+    let (kind, scope) = match head.split_once('(') {
+        Some((kind, scope)) => {
+            let scope = scope.strip_suffix(')').ok_or(format)?;
+            if scope.trim().is_empty() || scope.contains(['(', ')']) {
+                return Err(format.to_string());
+            }
+            (kind, Some(scope.to_string()))
+        }
+        None => (head, None),
+    };
+    if !rules.types.iter().any(|t| t == kind) {
+        return Err(format!(
+            "the type '{}' is not one of: {}",
+            kind,
+            rules.types.join(", ")
+        ));
+    }
+    if let Some(scope) = &scope {
+        if rules.scope_forbidden {
+            return Err("the subject must not have a scope".to_string());
+        }
+        if !rules.scopes.is_empty() && !rules.scopes.contains(scope) {
+            return Err(format!(
+                "the scope '{}' is not one of: {}",
+                scope,
+                rules.scopes.join(", ")
+            ));
+        }
+    } else if rules.scope_required {
+        return Err("the subject must have a scope, such as 'type(scope): description'".to_string());
+    }
+    if description.trim().is_empty() {
+        return Err("the description after the colon is empty".to_string());
+    }
+    if let Some(case) = &rules.subject_case {
+        let matched = case.cases.iter().any(|name| matches_case(description.trim(), name));
+        if matched != case.always {
+            return Err(format!(
+                "the description '{}' {} {}",
+                description.trim(),
+                if case.always { "must be in" } else { "must not be in" },
+                case.cases.join(" or ")
+            ));
+        }
+    }
+    let length = subject.chars().count();
+    if length > rules.max_length {
+        return Err(format!(
+            "the subject is {} characters long, it must be at most {} characters",
+            length, rules.max_length
+        ));
+    }
+    Ok(Subject {
+        kind: kind.to_string(),
+        scope,
+        breaking,
+        description: description.trim().to_string(),
+    })
+}
+
+/// Checks whether a subject starts with one of the types of the rules, such as
+/// `feat(parser)!: `, so that its scope and breaking change marker can be set.
+pub fn has_type(subject: &str, rules: &SubjectRules) -> bool {
+    subject.split_once(": ").is_some_and(|(head, _)| {
+        let kind = head.split(['(', '!']).next().unwrap_or_default();
+        rules.types.iter().any(|t| t == kind)
+    })
+}
+
+/// Sets the scope of a subject, replacing the scope chosen by the AI.
+///
+/// # Arguments
+///
+/// * `subject` - The repaired commit subject.
+/// * `scope` - The scope to set.
+///
+/// # Returns
+///
+/// * `String` containing the subject with the scope, unchanged if it has no type.
+pub fn with_scope(subject: &str, scope: &str) -> String {
+    let Some((head, description)) = subject.split_once(": ") else {
+        return subject.to_string();
+    };
+    let breaking = if head.ends_with('!') { "!" } else { "" };
+    let kind = head.split(['(', '!']).next().unwrap_or_default();
+    if kind.is_empty() || kind.contains(char::is_whitespace) {
+        return subject.to_string();
+    }
+    format!("{}({}){}: {}", kind, scope, breaking, description)
+}
+
+/// Marks a subject as a breaking change, such as `feat!: drop the v1 api`.
+///
+/// # Returns
+///
+/// * `String` containing the marked subject, unchanged if it has no type or is already marked.
+pub fn mark_breaking(subject: &str) -> String {
+    let Some((head, description)) = subject.split_once(": ") else {
+        return subject.to_string();
+    };
+    if head.ends_with('!') || head.is_empty() || head.contains(char::is_whitespace) {
+        return subject.to_string();
+    }
+    format!("{}!: {}", head, description)
+}
+
+/// Wraps the lines of a body longer than the limit at word boundaries.
+///
+/// # Arguments
+///
+/// * `body` - The body of the commit message.
+/// * `max_line_length` - The maximal length of a line, in characters.
+///
+/// # Returns
+///
+/// * `String` containing the wrapped body.
+pub fn wrap_body(body: &str, max_line_length: usize) -> String {
+    let mut wrapped: Vec<String> = Vec::new();
+    for line in body.lines() {
+        let mut current = String::new();
+        for word in line.split(' ') {
+            let length = current.chars().count() + word.chars().count() + 1;
+            if !current.is_empty() && length > max_line_length {
+                wrapped.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        wrapped.push(current);
+    }
+    wrapped.join("\n")
+}
+
+/// Checks whether a text is in a case named as by commitlint, unknown cases match any text.
+fn matches_case(text: &str, case: &str) -> bool {
+    let mut chars = text.chars();
+    let first = chars.next().unwrap_or_default();
+    let words = || text.split(|c: char| c.is_whitespace() || c == '-' || c == '_');
+    match case {
+        "lower-case" => text == text.to_lowercase(),
+        "upper-case" => text == text.to_uppercase(),
+        "sentence-case" => first.is_uppercase(),
+        "start-case" => words().all(|word| word.chars().next().is_none_or(char::is_uppercase)),
+        "pascal-case" => first.is_uppercase() && text.chars().all(char::is_alphanumeric),
+        "camel-case" => first.is_lowercase() && text.chars().all(char::is_alphanumeric),
+        "kebab-case" => text == text.to_lowercase() && !text.contains([' ', '_']),
+        "snake-case" => text == text.to_lowercase() && !text.contains([' ', '-']),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CaseRule, Subject, SubjectRules, has_type, mark_breaking, repair, validate, with_scope,
+        wrap_body,
+    };
+
+    #[test]
+    fn test_repair() {
+        let rules = SubjectRules::default();
+        assert_eq!(repair("```\nfeat: add login\n```", &rules), "feat: add login");
+        assert_eq!(repair("**\"Feat(API): add login.\"**", &rules), "feat(API): add login");
+        assert_eq!(repair("Subject: `fix:handle empty diff`", &rules), "fix: handle empty diff");
+        assert_eq!(repair("PROJ-42:add login", &rules), "PROJ-42:add login");
+    }
+
+    #[test]
+    fn test_validate() {
+        let rules = SubjectRules::default();
+        assert_eq!(
+            validate("feat(parser)!: drop legacy syntax", &rules),
+            Ok(Subject {
+                kind: "feat".into(),
+                scope: Some("parser".into()),
+                breaking: true,
+                description: "drop legacy syntax".into(),
+            })
+        );
+        assert!(validate("feature: add login", &rules).unwrap_err().contains("not one of"));
+        assert!(validate("add login", &rules).is_err());
+        assert!(validate("fix(): empty scope", &rules).is_err());
+        let long = format!("fix: {}", "x".repeat(80));
+        assert!(validate(&long, &rules).unwrap_err().contains("at most 50"));
+        assert!(validate("feat: add the loops and conditions to the parser", &rules).is_ok());
+        assert!(validate("feat: add the loops and the conditions to the parser", &rules).is_err());
+
+        let rules = SubjectRules {
+            scopes: vec!["parser".into()],
+            scope_required: true,
+            subject_case: Some(CaseRule {
+                always: false,
+                cases: vec!["sentence-case".into(), "upper-case".into()],
+            }),
+            ..Default::default()
+        };
+        assert!(validate("feat(parser): add loops", &rules).is_ok());
+        assert!(validate("feat: add loops", &rules).unwrap_err().contains("must have a scope"));
+        assert!(validate("feat(cli): add loops", &rules).unwrap_err().contains("not one of"));
+        assert!(validate("feat(parser): Add loops", &rules).unwrap_err().contains("sentence-case"));
+    }
+
+    #[test]
+    fn test_has_type() {
+        let rules = SubjectRules::default();
+        assert!(has_type("feat(parser)!: add loops", &rules));
+        assert!(has_type("fix: typo", &rules));
+        assert!(!has_type("PROJ-1: add loops", &rules));
+        assert!(!has_type("add loops", &rules));
+    }
+
+    #[test]
+    fn test_with_scope() {
+        assert_eq!(with_scope("feat: add loops", "parser"), "feat(parser): add loops");
+        assert_eq!(with_scope("fix(lexer)!: drop tabs", "parser"), "fix(parser)!: drop tabs");
+        assert_eq!(with_scope("add loops", "parser"), "add loops");
+    }
+
+    #[test]
+    fn test_mark_breaking() {
+        assert_eq!(mark_breaking("feat(api): drop v1"), "feat(api)!: drop v1");
+        assert_eq!(mark_breaking("feat!: drop v1"), "feat!: drop v1");
+        assert_eq!(mark_breaking("drop the v1 api"), "drop the v1 api");
+    }
+
+    #[test]
+    fn test_wrap_body() {
+        assert_eq!(
+            wrap_body("src/a.rs: Add a very long line (3)\nb.rs: Fix (1)", 20),
+            "src/a.rs: Add a very\nlong line (3)\nb.rs: Fix (1)"
+        );
+    }
+}
@@ -4,40 +4,116 @@ use std::io::Result;
 use toml::{Value, map::Map};
 
 use crate::{
-    cli::verbose::print_verbose,
-    constants::{CUSTOM_SECTION_NAME, DIFF_SIZE_LIMIT},
+    cli::{diff::DiffStrategy, repo_config, verbose::print_verbose},
+    constants::{
+        CUSTOM_SECTION_NAME, DEFAULT_CONCURRENCY, DEFAULT_SUBJECT_ATTEMPTS, DEFAULT_TOKEN_BUDGET,
+    },
 };
 
 static NAME: &str = "lines_limit";
+static TOKEN_BUDGET: &str = "token_budget";
+static DIFF_STRATEGY: &str = "diff_strategy";
+static CONCURRENCY: &str = "concurrency";
+static SUBJECT_ATTEMPTS: &str = "subject_attempts";
 
-pub fn get_lines_limit() -> usize {
-    let lines_limit = config::get_config_value(CUSTOM_SECTION_NAME, NAME);
-    if let Err(e) = lines_limit {
-        print_verbose(&format!(
-            "get custom config '{}' error: {:?}, return default: {}",
-            NAME, e, DIFF_SIZE_LIMIT
-        ));
-        return DIFF_SIZE_LIMIT;
+/// Returns the changed lines limit, when the user opted in to stop above it.
+///
+/// # Returns
+///
+/// * `Some(usize)` containing the limit set by `gim config --lines-limit`.
+/// * `None` if no limit is set: large changes are fitted into the token budget instead.
+pub fn get_lines_limit() -> Option<usize> {
+    let lines_limit = get_custom_value(NAME)?;
+    match lines_limit.as_integer() {
+        Some(limit) if limit > 0 => Some(limit as usize),
+        _ => None,
     }
-    let lines_limit = lines_limit.ok();
-    if let Some(limit) = lines_limit {
-        print_verbose(&format!("get custom config '{}' value: {:?}", NAME, limit));
-        return limit.as_integer().unwrap() as usize;
-    }
-    DIFF_SIZE_LIMIT
 }
 
 pub fn set_lines_limit(lines_limit: usize) -> Result<()> {
-    let set = config::update_config_value(
-        CUSTOM_SECTION_NAME,
-        NAME,
-        Value::Integer(lines_limit as i64),
-    );
+    set_custom_value(NAME, Value::Integer(lines_limit as i64))
+}
+
+/// Returns the count of tokens the changes sent to the AI may use.
+pub fn get_token_budget() -> usize {
+    get_custom_value(TOKEN_BUDGET)
+        .and_then(|v| v.as_integer())
+        .filter(|budget| *budget > 0)
+        .map(|budget| budget as usize)
+        .unwrap_or(DEFAULT_TOKEN_BUDGET)
+}
+
+pub fn set_token_budget(token_budget: usize) -> Result<()> {
+    set_custom_value(TOKEN_BUDGET, Value::Integer(token_budget as i64))
+}
+
+/// Returns how the changes are sent to the AI, `single` by default.
+pub fn get_diff_strategy() -> DiffStrategy {
+    let Some(value) = get_custom_value(DIFF_STRATEGY) else {
+        return DiffStrategy::Single;
+    };
+    let name = value.as_str().unwrap_or_default();
+    DiffStrategy::from_name(name).unwrap_or_else(|| {
+        eprintln!(
+            "Warning: unknown diff strategy '{}', expected one of: {}",
+            name,
+            DiffStrategy::NAMES.join(", ")
+        );
+        DiffStrategy::Single
+    })
+}
+
+pub fn set_diff_strategy(diff_strategy: &str) -> Result<()> {
+    set_custom_value(DIFF_STRATEGY, Value::String(diff_strategy.to_string()))
+}
+
+/// Returns the count of AI requests sent at the same time.
+pub fn get_concurrency() -> usize {
+    get_custom_value(CONCURRENCY)
+        .and_then(|v| v.as_integer())
+        .filter(|concurrency| *concurrency > 0)
+        .map(|concurrency| concurrency as usize)
+        .unwrap_or(DEFAULT_CONCURRENCY)
+}
+
+pub fn set_concurrency(concurrency: usize) -> Result<()> {
+    set_custom_value(CONCURRENCY, Value::Integer(concurrency as i64))
+}
+
+/// Returns the count of times the AI is asked for a subject following Conventional Commits,
+/// `0` when the subject is not checked.
+pub fn get_subject_attempts() -> usize {
+    get_custom_value(SUBJECT_ATTEMPTS)
+        .and_then(|v| v.as_integer())
+        .filter(|attempts| *attempts >= 0)
+        .map(|attempts| attempts as usize)
+        .unwrap_or(DEFAULT_SUBJECT_ATTEMPTS)
+}
+
+pub fn set_subject_attempts(subject_attempts: usize) -> Result<()> {
+    set_custom_value(SUBJECT_ATTEMPTS, Value::Integer(subject_attempts as i64))
+}
+
+fn get_custom_value(name: &str) -> Option<Value> {
+    match repo_config::get_config_value(CUSTOM_SECTION_NAME, name) {
+        Ok(value) => {
+            print_verbose(&format!("get custom config '{}' value: {:?}", name, value));
+            Some(value)
+        }
+        Err(e) => {
+            print_verbose(&format!(
+                "get custom config '{}' error: {:?}, use default",
+                name, e
+            ));
+            None
+        }
+    }
+}
+
+fn set_custom_value(name: &str, value: Value) -> Result<()> {
+    let set = config::update_config_value(CUSTOM_SECTION_NAME, name, value.clone());
     if let Err(e) = set {
-        print_verbose(&format!(
-            "get custom config '{}' error: {:?}, return default: {}",
-            NAME, e, DIFF_SIZE_LIMIT
-        ));
+        print_verbose(&format!("set custom config '{}' error: {:?}", name, e));
         if e.kind() == ErrorKind::NotFound
             && e.to_string() == format!("Section '{}' not found", CUSTOM_SECTION_NAME)
         {
@@ -45,15 +121,12 @@ pub fn set_lines_limit(lines_limit: usize) -> Result<()> {
             let map = config.as_table_mut().unwrap();
 
             let mut update_table = Map::new();
-            update_table.insert(NAME.to_string(), Value::Integer(lines_limit as i64));
+            update_table.insert(name.to_string(), value);
             map.insert(CUSTOM_SECTION_NAME.to_string(), Value::Table(update_table));
             return config::save_config(&config);
         }
         return Err(e);
     }
-    println!(
-        "set custom config '{}' done, value: {:?}",
-        NAME, lines_limit
-    );
+    println!("set custom config '{}' done, value: {}", name, value);
     Ok(())
 }
@@ -0,0 +1,568 @@
+use std::process::Command;
+
+use crate::cli::{budget, ignore, placeholder, verbose::print_verbose};
+
+/// How the changes are sent to the AI.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiffStrategy {
+    /// All the changes in one request, fitted into the token budget.
+    Single,
+    /// The changes split into chunks within the token budget, summarized concurrently.
+    Chunked,
+    /// Every file summarized by its own request, concurrently.
+    PerFile,
+}
+
+impl DiffStrategy {
+    /// The names of the strategies, as set in the config file.
+    pub const NAMES: [&str; 3] = ["single", "chunked", "per-file"];
+
+    /// Returns the strategy of the given name, `None` if the name is unknown.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "single" => Some(DiffStrategy::Single),
+            "chunked" => Some(DiffStrategy::Chunked),
+            "per-file" => Some(DiffStrategy::PerFile),
+            _ => None,
+        }
+    }
+}
+
+/// The changes collected to be described by the AI.
+#[derive(Debug)]
+pub enum Changes {
+    /// The changes in one chunk, or split into several chunks.
+    Chunks(Vec<String>),
+    /// The changes of every file on their own.
+    Files(Vec<FileChange>),
+}
+
+impl Changes {
+    /// Returns the contents sent to the AI, to be scanned or rewritten before they are sent.
+    pub fn contents_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            Changes::Chunks(chunks) => chunks.iter_mut().collect(),
+            Changes::Files(files) => files.iter_mut().map(|file| &mut file.content).collect(),
+        }
+    }
+
+    /// Checks whether there are no changes.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Changes::Chunks(chunks) => chunks.is_empty(),
+            Changes::Files(files) => files.is_empty(),
+        }
+    }
+}
+
+/// The changes of a file.
+#[derive(Debug)]
+pub struct FileChange {
+    /// The path of the file, the new one when it was renamed.
+    pub path: String,
+    /// The status of the file given by `--name-status`, such as `M`, `D` or `R100`.
+    pub status: String,
+    /// The count of added and removed lines.
+    pub changed_lines: usize,
+    /// Whether the content of the file is left out by the ignore rules, see [`ignore`].
+    pub ignored: bool,
+    /// The changes of the file, formatted as [`staged_changes`] does.
+    pub content: String,
+}
+
+const STAGED_INTRO: &str = "When I use `git diff`, I got the following output: \n";
+const STAGED_DETAIL_INTRO: &str =
+    "\nDetailed changes for added/modified files (excluding deleted files):\n";
+const AMEND_INTRO: &str =
+    "As I want to amend commit message, I use `git show` and got the following output: \n";
+const AMEND_DETAIL_INTRO: &str =
+    "\nDetailed changes for added/modified files in last commit (excluding deleted files):\n";
+
+/// Collects the staged changes to be described by the AI.
+///
+/// The file status of every staged file is listed, while the full diff is only
+/// included for added and modified files to reduce the token consumption.
+/// The full diff is fitted into the token budget, see [`budget::fit_diff`].
+///
+/// # Arguments
+///
+/// * `token_budget` - The count of tokens the changes may use.
+///
+/// # Returns
+///
+/// * `String` containing the staged changes, empty if nothing is staged.
+pub fn staged_changes(token_budget: usize) -> String {
+    let mut diff_content = String::new();
+
+    // Get staged changes with name-status to filter out deleted file contents
+    let diff_output = Command::new("git")
+        .args(["diff", "--cached", "--name-status"])
+        .output()
+        .expect("Failed to get git diff --cached --name-status");
+    print_verbose("Run 'git diff --cached --name-status'");
+
+    // Get full diff for non-deleted and not ignored files
+    let full_diff = staged_full_diff();
+
+    if !diff_output.stdout.is_empty() {
+        diff_content.push_str(STAGED_INTRO);
+
+        // Add file status information (including deleted files)
+        let status_info = String::from_utf8_lossy(&diff_output.stdout);
+        diff_content.push_str(&status_info);
+        diff_content.push('\n');
+
+        // Add full diff content only for added/modified files
+        if !full_diff.is_empty() {
+            diff_content.push_str(STAGED_DETAIL_INTRO);
+            let diff_budget = token_budget.saturating_sub(budget::estimate_tokens(&diff_content));
+            diff_content.push_str(&fit_full_diff(
+                &full_diff,
+                diff_budget,
+                &["diff", "--cached", "--stat"],
+            ));
+            diff_content.push('\n');
+        }
+    }
+    diff_content
+}
+
+/// Collects the changes of the last commit, used when amending it.
+///
+/// # Arguments
+///
+/// * `token_budget` - The count of tokens the changes may use.
+///
+/// # Returns
+///
+/// * `String` containing the changes of `HEAD`.
+pub fn last_commit_changes(token_budget: usize) -> String {
+    let mut diff_content = String::new();
+    diff_content.push_str(AMEND_INTRO);
+
+    // Get last commit changes with name-status to filter out deleted file contents
+    let show_status_output = Command::new("git")
+        .args(["show", "--pretty=format:", "--name-status", "HEAD"])
+        .output()
+        .expect("Failed to get git show --name-status");
+    print_verbose("Run 'git show --pretty=format: --name-status HEAD'");
+
+    // Get full diff for non-deleted and not ignored files in last commit
+    let show_diff = last_commit_full_diff();
+
+    // Add file status information (including deleted files)
+    let status_info = String::from_utf8_lossy(&show_status_output.stdout);
+    diff_content.push_str(&status_info);
+    diff_content.push('\n');
+
+    // Add full diff content only for added/modified files
+    if !show_diff.is_empty() {
+        diff_content.push_str(AMEND_DETAIL_INTRO);
+        let diff_budget = token_budget.saturating_sub(budget::estimate_tokens(&diff_content));
+        diff_content.push_str(&fit_full_diff(
+            &show_diff,
+            diff_budget,
+            &["show", "--pretty=format:", "--stat", "HEAD"],
+        ));
+        diff_content.push('\n');
+    }
+    diff_content
+}
+
+/// Collects the changes to be described by the AI according to the diff strategy.
+///
+/// # Arguments
+///
+/// * `strategy` - How the changes are sent to the AI.
+/// * `token_budget` - The count of tokens the changes, or each chunk of them, may use.
+/// * `staged` - Whether the staged changes are described.
+/// * `amend` - Whether the changes of the last commit are described.
+///
+/// # Returns
+///
+/// * `Changes` containing a single chunk unless the strategy splits the changes, empty if there
+///   are no changes.
+pub fn collect_changes(
+    strategy: DiffStrategy,
+    token_budget: usize,
+    staged: bool,
+    amend: bool,
+) -> Changes {
+    let mut chunks = Vec::new();
+    match strategy {
+        DiffStrategy::Single => {
+            let mut diff_content = String::new();
+            if staged {
+                diff_content.push_str(&staged_changes(token_budget));
+            }
+            if amend {
+                // The last commit changes get what is left of the budget
+                let left = token_budget.saturating_sub(budget::estimate_tokens(&diff_content));
+                diff_content.push_str(&last_commit_changes(left));
+            }
+            chunks.push(diff_content);
+        }
+        DiffStrategy::Chunked => {
+            if staged {
+                chunks.extend(staged_chunks(token_budget));
+            }
+            if amend {
+                chunks.extend(last_commit_chunks(token_budget));
+            }
+        }
+        DiffStrategy::PerFile => {
+            let mut files = Vec::new();
+            if staged {
+                files.extend(staged_files(token_budget));
+            }
+            if amend {
+                files.extend(last_commit_files(token_budget));
+            }
+            return Changes::Files(files);
+        }
+    }
+    chunks.retain(|chunk| !chunk.is_empty());
+    Changes::Chunks(chunks)
+}
+
+/// Collects the staged changes split into chunks within the token budget, each formatted as
+/// [`staged_changes`] does. Files are kept in the order of git, so that the files of a
+/// directory mostly end in the same chunk.
+///
+/// # Arguments
+///
+/// * `token_budget` - The count of tokens a chunk may use.
+///
+/// # Returns
+///
+/// * `Vec<String>` containing the chunks, empty if nothing is staged.
+pub fn staged_chunks(token_budget: usize) -> Vec<String> {
+    let status = git_output(&["diff", "--cached", "--name-status"]);
+    let full_diff = staged_full_diff();
+    chunk_changes(
+        &pair_files(&status, &full_diff),
+        token_budget,
+        STAGED_INTRO,
+        STAGED_DETAIL_INTRO,
+    )
+}
+
+/// Collects the changes of the last commit split into chunks within the token budget.
+///
+/// # Arguments
+///
+/// * `token_budget` - The count of tokens a chunk may use.
+///
+/// # Returns
+///
+/// * `Vec<String>` containing the chunks of the changes of `HEAD`.
+pub fn last_commit_chunks(token_budget: usize) -> Vec<String> {
+    let status = git_output(&["show", "--pretty=format:", "--name-status", "HEAD"]);
+    let full_diff = last_commit_full_diff();
+    chunk_changes(
+        &pair_files(&status, &full_diff),
+        token_budget,
+        AMEND_INTRO,
+        AMEND_DETAIL_INTRO,
+    )
+}
+
+/// Collects the changes of every staged file, in the order of `git diff --cached --name-status`.
+///
+/// # Arguments
+///
+/// * `token_budget` - The count of tokens the changes of a file may use.
+///
+/// # Returns
+///
+/// * `Vec<FileChange>` containing the changes of the files, empty if nothing is staged.
+pub fn staged_files(token_budget: usize) -> Vec<FileChange> {
+    let status = git_output(&["diff", "--cached", "--name-status"]);
+    let full_diff = staged_full_diff();
+    let numstat = git_output(&["diff", "--cached", "--numstat"]);
+    file_changes(
+        &pair_files(&status, &full_diff),
+        &numstat,
+        token_budget,
+        STAGED_INTRO,
+        STAGED_DETAIL_INTRO,
+    )
+}
+
+/// Collects the changes of every file of the last commit.
+///
+/// # Arguments
+///
+/// * `token_budget` - The count of tokens the changes of a file may use.
+///
+/// # Returns
+///
+/// * `Vec<FileChange>` containing the changes of the files of `HEAD`.
+pub fn last_commit_files(token_budget: usize) -> Vec<FileChange> {
+    let status = git_output(&["show", "--pretty=format:", "--name-status", "HEAD"]);
+    let full_diff = last_commit_full_diff();
+    let numstat = git_output(&["show", "--pretty=format:", "--numstat", "HEAD"]);
+    file_changes(
+        &pair_files(&status, &full_diff),
+        &numstat,
+        token_budget,
+        AMEND_INTRO,
+        AMEND_DETAIL_INTRO,
+    )
+}
+
+/// The `--name-status` line of a file, its path and its diff, empty for deleted files.
+struct StatusFile<'a> {
+    line: &'a str,
+    path: &'a str,
+    diff: &'a str,
+}
+
+fn pair_files<'a>(status: &'a str, full_diff: &'a str) -> Vec<StatusFile<'a>> {
+    let file_diffs = budget::split_files(full_diff);
+    status
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let path = line.rsplit('\t').next().unwrap_or(line);
+            let diff = file_diffs
+                .iter()
+                .find(|file| file.path == path)
+                .map(|file| file.text)
+                .unwrap_or_default();
+            StatusFile { line, path, diff }
+        })
+        .collect()
+}
+
+fn chunk_changes(
+    files: &[StatusFile],
+    token_budget: usize,
+    intro: &str,
+    detail_intro: &str,
+) -> Vec<String> {
+    let sizes: Vec<usize> = files
+        .iter()
+        .map(|file| budget::estimate_tokens(file.line) + budget::estimate_tokens(file.diff) + 1)
+        .collect();
+    let chunk_budget = token_budget
+        .saturating_sub(budget::estimate_tokens(intro) + budget::estimate_tokens(detail_intro));
+
+    let chunks = budget::chunk(&sizes, chunk_budget);
+    print_verbose(&format!("Split {} files into {} chunks", files.len(), chunks.len()));
+    chunks
+        .into_iter()
+        .map(|indexes| {
+            let chunk: Vec<&StatusFile> = indexes.iter().map(|&i| &files[i]).collect();
+            format_chunk(&chunk, token_budget, intro, detail_intro)
+        })
+        .collect()
+}
+
+fn file_changes(
+    files: &[StatusFile],
+    numstat: &str,
+    token_budget: usize,
+    intro: &str,
+    detail_intro: &str,
+) -> Vec<FileChange> {
+    files
+        .iter()
+        .map(|file| {
+            let status = file.line.split('\t').next().unwrap_or_default();
+            FileChange {
+                path: file.path.to_string(),
+                status: status.to_string(),
+                changed_lines: changed_lines(numstat, file.path),
+                // Added and modified files have a diff, unless it was excluded
+                ignored: file.diff.is_empty() && (status == "A" || status == "M"),
+                content: format_chunk(&[file], token_budget, intro, detail_intro),
+            }
+        })
+        .collect()
+}
+
+/// Formats files as [`staged_changes`] does, a file larger than the budget gets truncated.
+fn format_chunk(
+    files: &[&StatusFile],
+    token_budget: usize,
+    intro: &str,
+    detail_intro: &str,
+) -> String {
+    let mut content = intro.to_string();
+    let mut diffs = String::new();
+    for file in files {
+        content.push_str(file.line);
+        content.push('\n');
+        diffs.push_str(file.diff);
+    }
+    if !diffs.is_empty() {
+        content.push_str(detail_intro);
+        let left = token_budget.saturating_sub(budget::estimate_tokens(&content));
+        content.push_str(&budget::fit_diff(&diffs, left).diff);
+        content.push('\n');
+    }
+    content
+}
+
+/// Returns the count of added and removed lines of a file from the output of `--numstat`.
+fn changed_lines(numstat: &str, path: &str) -> usize {
+    numstat
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let (added, removed, file) = (fields.next()?, fields.next()?, fields.next()?);
+            (file == path).then(|| {
+                added.parse::<usize>().unwrap_or(0) + removed.parse::<usize>().unwrap_or(0)
+            })
+        })
+        .sum()
+}
+
+/// Fits a full diff into the token budget, the files left out are summarized by `--stat`.
+fn fit_full_diff(full_diff: &str, token_budget: usize, stat_args: &[&str]) -> String {
+    let fitted = budget::fit_diff(full_diff, token_budget);
+    if fitted.truncated.is_empty() && fitted.summarized.is_empty() {
+        return fitted.diff;
+    }
+    eprintln!(
+        "Note: the changes exceed the token budget, {} file(s) truncated and {} file(s) summarized",
+        fitted.truncated.len(),
+        fitted.summarized.len()
+    );
+    print_verbose(&format!(
+        "Truncated files: {:?}, summarized files: {:?}",
+        fitted.truncated, fitted.summarized
+    ));
+
+    let mut content = fitted.diff;
+    if !fitted.summarized.is_empty() {
+        let mut args: Vec<&str> = stat_args.to_vec();
+        args.push("--");
+        args.extend(fitted.summarized.iter().map(String::as_str));
+        content.push_str(
+            "\nThe following files are too large, only their changed lines count is given:\n",
+        );
+        content.push_str(&git_output(&args));
+        content.push('\n');
+    }
+    content
+}
+
+/// Counts the changed files and lines of the staged changes, and of the last commit when amending it.
+///
+/// # Arguments
+///
+/// * `amend` - Whether the changes of the last commit are included.
+///
+/// # Returns
+///
+/// * `(usize, usize)` containing the count of changed files and the count of added and removed lines,
+///   leaving out the ignored files as their content is not sent.
+pub fn numstat(amend: bool) -> (usize, usize) {
+    let mut commands = vec![content_args(&["diff", "--cached", "--numstat"])];
+    if amend {
+        commands.push(content_args(&["show", "--pretty=format:", "--numstat", "HEAD"]));
+    }
+    let mut files = 0;
+    let mut lines = 0;
+    for args in commands {
+        let Ok(output) = Command::new("git").args(&args).output() else {
+            continue;
+        };
+        print_verbose(&format!("Run 'git {}'", args.join(" ")));
+        // <added> <removed> <path>, binary files count as '-'
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let mut fields = line.split('\t');
+            let (Some(added), Some(removed)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            files += 1;
+            lines += added.parse::<usize>().unwrap_or(0) + removed.parse::<usize>().unwrap_or(0);
+        }
+    }
+    (files, lines)
+}
+
+/// Returns the current branch, empty when HEAD is detached.
+pub fn current_branch() -> String {
+    git_output(&["branch", "--show-current"])
+}
+
+/// Returns the subjects of the last commits, the newest first, one per line.
+///
+/// # Arguments
+///
+/// * `count` - The maximal count of commits.
+pub fn recent_commits(count: usize) -> String {
+    git_output(&["log", "-n", &count.to_string(), "--pretty=format:%s"])
+}
+
+/// Returns the name of the directory of the repository.
+pub fn repo_name() -> String {
+    let top_level = git_output(&["rev-parse", "--show-toplevel"]);
+    std::path::Path::new(&top_level)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Returns the diff of the staged changes, and of the last commit when amending, with the
+/// deleted files but without the ignored ones, for the analysis of the breaking changes.
+pub fn complete_diff(amend: bool) -> String {
+    let mut complete = git_output(&content_args(&["diff", "--cached"]));
+    if amend {
+        complete.push('\n');
+        complete.push_str(&git_output(&content_args(&["show", "--pretty=format:", "HEAD"])));
+    }
+    complete
+}
+
+/// Returns the diff of the staged added and modified files, see [`full_diff`].
+fn staged_full_diff() -> String {
+    full_diff(&["diff", "--cached", "--diff-filter=AM"], "HEAD", "")
+}
+
+/// Returns the diff of the added and modified files of the last commit, see [`full_diff`].
+fn last_commit_full_diff() -> String {
+    full_diff(&["show", "--pretty=format:", "--diff-filter=AM", "HEAD"], "HEAD^", "HEAD")
+}
+
+/// Runs a git command giving a diff, leaving out the ignored files and replacing the content of
+/// binary, minified and generated files by a placeholder, see [`placeholder::replace_unreadable`].
+fn full_diff(args: &[&str], old_rev: &str, new_rev: &str) -> String {
+    let full_diff = git_output(&content_args(args));
+    placeholder::replace_unreadable(&full_diff, old_rev, new_rev)
+}
+
+/// Appends the pathspecs excluding the ignored paths to a git command collecting the content
+/// of the changes, see [`ignore::exclude_pathspecs`].
+fn content_args<'a>(args: &[&'a str]) -> Vec<&'a str> {
+    let mut args = args.to_vec();
+    let excludes = ignore::exclude_pathspecs();
+    if !excludes.is_empty() {
+        args.push("--");
+        args.extend(excludes.iter().map(String::as_str));
+    }
+    args
+}
+
+/// Runs a git command and returns its trimmed output, empty if it failed.
+pub fn git_output(args: &[&str]) -> String {
+    print_verbose(&format!("Run 'git {}'", args.join(" ")));
+    match Command::new("git").args(args).output() {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+/// Checks whether the current directory is inside a git work tree.
+pub fn is_git_repository() -> bool {
+    // git rev-parse --is-inside-work-tree
+    Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
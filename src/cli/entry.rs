@@ -1,20 +1,38 @@
 use crate::{
     cli::{
-        http::get_url_by_model,
-        prompt::{get_diff_prompt, get_subject_prompt},
+        ai_configer::AiConfig,
+        ai_profile,
+        http::{get_url_by_model, is_local_url},
+        provider::registry,
+        prompt::{get_diff_prompt, get_subject_prompt, repo_prompt_path},
+    },
+    constants::{
+        DEFAULT_CONNECT_TIMEOUT_SECS, DEFAULT_MAX_RETRIES, DEFAULT_READ_TIMEOUT_SECS,
+        DIFF_PROMPT_FILE, SUBJECT_PROMPT_FILE,
     },
-    constants::{DIFF_PROMPT_FILE, SUBJECT_PROMPT_FILE},
     verbose::print_verbose,
 };
 
 use super::{
-    command::{GimCli, GimCommands},
-    http::chat,
+    breaking,
+    command::{AiAction, GimCli, GimCommands, HookAction, ProfileAction},
+    conventional::{self, SubjectRules},
+    custom_param,
+    diff::{self, Changes},
+    http::chat_with_fallback,
+    lint,
+    review::{self, ReviewAction},
+    secret, summarize,
+    template::{self, PromptContext},
+    ticket,
+    trailer::Trailers,
 };
-use gim_config::config;
 use gim_config::directory;
 use indoc::{eprintdoc, printdoc};
-use std::process::Command;
+use std::{io::IsTerminal, process::Command};
+
+/// The start of the subject put in place of the one the AI failed to generate.
+pub const SUBJECT_ERROR_PREFIX: &str = "Error: ";
 
 /// Runs the main CLI logic based on the provided arguments and configuration.
 ///
@@ -72,11 +90,12 @@ pub async fn run_cli(cli: &GimCli, mut config: toml::Value) {
             prompt,
             editor,
             reset,
+            render,
         }) => {
             if *reset {
-                if *edit || prompt.is_some() || editor.is_some() {
+                if *edit || prompt.is_some() || editor.is_some() || *render {
                     println!(
-                        "Warning: --edit, --prompt or --editor will be ignored when --reset provided"
+                        "Warning: --edit, --prompt, --editor or --render will be ignored when --reset provided"
                     );
                 }
                 // delete the 2 files
@@ -85,7 +104,7 @@ pub async fn run_cli(cli: &GimCli, mut config: toml::Value) {
                     std::process::exit(1);
                 }
             } else if let Err(e) =
-                handle_prompt_command(*edit, prompt.as_deref(), editor.as_deref())
+                handle_prompt_command(*edit, prompt.as_deref(), editor.as_deref(), *render)
             {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
@@ -97,45 +116,119 @@ pub async fn run_cli(cli: &GimCli, mut config: toml::Value) {
             apikey,
             url,
             language,
+            provider,
+            action,
         }) => {
-            if model.is_none() && apikey.is_none() && url.is_none() && language.is_none() {
-                let ai = get_validated_ai_config(false, false);
+            if let Some(AiAction::Profile { action }) = action {
+                let result = match action {
+                    ProfileAction::Add {
+                        name,
+                        model,
+                        apikey,
+                        url,
+                        language,
+                        provider,
+                    } => ai_profile::add_profile(
+                        &mut config,
+                        name,
+                        &[
+                            ("model", model),
+                            ("apikey", apikey),
+                            ("url", url),
+                            ("language", language),
+                            ("provider", provider),
+                        ],
+                    ),
+                    ProfileAction::List => {
+                        ai_profile::list_profiles(&config);
+                        Ok(())
+                    }
+                    ProfileAction::Use { name } => ai_profile::use_profile(&mut config, name),
+                    ProfileAction::Remove { name } => ai_profile::remove_profile(&mut config, name),
+                };
+                if let Err(e) = result {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            if model.is_none()
+                && apikey.is_none()
+                && url.is_none()
+                && language.is_none()
+                && provider.is_none()
+            {
+                let ai = get_validated_ai_config(false, false, cli.profile.as_deref());
                 if let Some(ai) = ai {
-                    let mut url = ai.0;
-                    if url.is_empty() && !ai.1.is_empty() {
-                        if let Some(str) = get_url_by_model(&ai.1) {
+                    let mut url = ai.url;
+                    if url.is_empty() && !ai.model.is_empty() {
+                        if let Some(str) = get_url_by_model(&ai.model) {
                             url = format!("(not configured. Will use default : {})", str);
                         } else {
                             eprintln!("Warning: you have not setup api url by 'gim ai -u <url>'");
                         }
                     }
+                    if let Some(profile) = cli.profile.as_deref().or_else(|| {
+                        config.get("ai").and_then(ai_profile::active_profile)
+                    }) {
+                        println!("Profile:    {}", profile);
+                    }
                     printdoc!(
                         r#"
                         Model:      {}
                         API Key:    {}
                         URL:        {}
                         Language:   {}
-                        You can use 'gim ai -m <model> -k <apikey> -u <url> -l <language>' respectively to update the configuration
+                        Provider:   {}
+                        You can use 'gim ai -m <model> -k <apikey> -u <url> -l <language> -p <provider>' respectively to update the configuration
                         "#,
-                        &ai.1,
-                        &ai.2,
+                        &ai.model,
+                        &ai.apikey,
                         &url,
-                        &ai.3
+                        &ai.language,
+                        ai.provider.clone().unwrap_or_else(|| format!(
+                            "(not configured. Inferred from the model name: {})",
+                            registry::provider_for_model(&ai.model).name()
+                        ))
                     );
+                    if !ai.fallbacks.is_empty() {
+                        let models: Vec<&str> =
+                            ai.fallbacks.iter().map(|f| f.model.as_str()).collect();
+                        println!("Fallbacks:  {}", models.join(" -> "));
+                    }
                 } else {
                     eprintln!("Error: ai section is not configured");
                 }
                 return;
             }
-            super::ai_configer::update_ai_config(&mut config, model, apikey, url, language);
+            super::ai_configer::update_ai_config(
+                &mut config,
+                model,
+                apikey,
+                url,
+                language,
+                provider,
+            );
+            if let Some(profile) = config.get("ai").and_then(ai_profile::active_profile)
+                && (model.is_some() || apikey.is_some() || url.is_some() || provider.is_some())
+            {
+                eprintln!(
+                    "Warning: the active profile '{}' overrides the model, url, api key and provider, use 'gim ai profile add {} ...' to update it",
+                    profile, profile
+                );
+            }
             return;
         }
         Some(GimCommands::Config {
             lines_limit,
+            token_budget,
+            diff_strategy,
+            concurrency,
+            subject_attempts,
             show_location,
         }) => {
             if *show_location {
-                if let Err(e) = config::get_config_and_print() {
+                if let Err(e) = super::repo_config::print_config_sources() {
                     eprintln!("Error: {}", e);
                     std::process::exit(1);
                 }
@@ -144,26 +237,74 @@ pub async fn run_cli(cli: &GimCli, mut config: toml::Value) {
                 }
             }
             if let Some(lines_limit) = lines_limit
-                && let Err(e) = super::custom_param::set_lines_limit(*lines_limit)
+                && let Err(e) = custom_param::set_lines_limit(*lines_limit)
+            {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            if let Some(token_budget) = token_budget
+                && let Err(e) = custom_param::set_token_budget(*token_budget)
             {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
+            if let Some(diff_strategy) = diff_strategy
+                && let Err(e) = custom_param::set_diff_strategy(diff_strategy)
+            {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            if let Some(concurrency) = concurrency
+                && let Err(e) = custom_param::set_concurrency(*concurrency)
+            {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            if let Some(subject_attempts) = subject_attempts
+                && let Err(e) = custom_param::set_subject_attempts(*subject_attempts)
+            {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(GimCommands::Hook { action }) => {
+            let result = match action {
+                HookAction::Install { force } => super::hook::install_hook(*force),
+                HookAction::Uninstall => super::hook::uninstall_hook(),
+                HookAction::Run {
+                    message_file,
+                    source,
+                    ..
+                } => {
+                    super::hook::run_hook(message_file, source.as_deref(), cli.verbose).await;
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
             return;
         }
         None => {}
     }
 
     // Check if current directory is a git repository
-    // git rev-parse --is-inside-work-tree
-    let is_git_repo = Command::new("git")
-        .args(["rev-parse", "--is-inside-work-tree"])
-        .output();
-    if is_git_repo.is_err() || !is_git_repo.unwrap().status.success() {
+    if !diff::is_git_repository() {
         eprintln!("Error: should run in a git repository");
         return;
     }
 
+    // In dry-run mode nothing is staged, so only the changes already staged are used
+    let auto_add = cli.auto_add && !cli.dry_run;
+    if cli.dry_run && cli.auto_add {
+        eprintln!("Warning: '--auto-add' is ignored in dry-run mode, only staged changes are used");
+    }
+
+    let token_budget = custom_param::get_token_budget();
+    let diff_strategy = custom_param::get_diff_strategy();
+
     // Get git status
     // git status -s --untracked-files=no
     let status_output = Command::new("git")
@@ -172,34 +313,38 @@ pub async fn run_cli(cli: &GimCli, mut config: toml::Value) {
             "-s",
             &format!(
                 "--untracked-files={}",
-                if cli.auto_add { "all" } else { "no" }
+                if auto_add { "all" } else { "no" }
             ),
         ])
         .output()
         .expect("Failed to get git status");
     print_verbose(&format!(
         "Run 'git status -s --untracked-files={}'",
-        if cli.auto_add { "all" } else { "no" }
+        if auto_add { "all" } else { "no" }
     ));
     let status_str = String::from_utf8_lossy(&status_output.stdout);
     let changes: Vec<&str> = status_str.lines().collect();
-    let mut diff_content = String::new();
+    let mut saved_index: Option<String> = None;
     if !changes.is_empty() {
-        println!("Found {} changes:", changes.len());
+        print_progress(cli.dry_run, &format!("Found {} changes:", changes.len()));
         for entry in changes.iter() {
-            println!(
-                "{:?} {}",
-                entry,
-                if !cli.auto_add && (entry.starts_with(' ') || entry.starts_with('?')) {
-                    " - <<Ignored>>"
-                } else {
-                    ""
-                }
+            print_progress(
+                cli.dry_run,
+                &format!(
+                    "{:?} {}",
+                    entry,
+                    if !auto_add && (entry.starts_with(' ') || entry.starts_with('?')) {
+                        " - <<Ignored>>"
+                    } else {
+                        ""
+                    }
+                ),
             );
         }
 
-        // Auto add changes if enabled
-        if cli.auto_add {
+        // Auto add changes if enabled, the index is saved first so that an abort restores it
+        if auto_add {
+            saved_index = Some(diff::git_output(&["write-tree"])).filter(|tree| !tree.is_empty());
             let add_output = Command::new("git")
                 .args(["add", "."])
                 .output()
@@ -210,156 +355,142 @@ pub async fn run_cli(cli: &GimCli, mut config: toml::Value) {
             }
             print_verbose("Run 'git add .'");
         }
-
-        // Get staged changes with name-status to filter out deleted file contents
-        let diff_output = Command::new("git")
-            .args(["diff", "--cached", "--name-status"])
-            .output()
-            .expect("Failed to get git diff --cached --name-status");
-        print_verbose("Run 'git diff --cached --name-status'");
-
-        // Get full diff for non-deleted files
-        let full_diff_output = Command::new("git")
-            .args(["diff", "--cached", "--diff-filter=AM"])
-            .output()
-            .expect("Failed to get git diff --cached --diff-filter=AM");
-        print_verbose("Run 'git diff --cached --diff-filter=AM'");
-
-        if !diff_output.stdout.is_empty() {
-            diff_content.push_str("When I use `git diff`, I got the following output: \n");
-
-            // Add file status information (including deleted files)
-            let status_info = String::from_utf8_lossy(&diff_output.stdout);
-            diff_content.push_str(&status_info);
-            diff_content.push('\n');
-
-            // Add full diff content only for added/modified files
-            if !full_diff_output.stdout.is_empty() {
-                diff_content.push_str(
-                    "\nDetailed changes for added/modified files (excluding deleted files):\n",
-                );
-                diff_content.push_str(&String::from_utf8_lossy(&full_diff_output.stdout));
-                diff_content.push('\n');
-            }
-        }
     }
+    let mut changes_to_describe = diff::collect_changes(
+        diff_strategy,
+        token_budget,
+        !changes.is_empty(),
+        cli.overwrite,
+    );
     if cli.overwrite {
-        diff_content.push_str(
-            "As I want to amend commit message, I use `git show` and got the following output: \n",
-        );
-
-        // Get last commit changes with name-status to filter out deleted file contents
-        let show_status_output = Command::new("git")
-            .args(["show", "--pretty=format:", "--name-status", "HEAD"])
-            .output()
-            .expect("Failed to get git show --name-status");
-        print_verbose("Run 'git show --pretty=format: --name-status HEAD'");
-
-        // Get full diff for non-deleted files in last commit
-        let show_diff_output = Command::new("git")
-            .args(["show", "--pretty=format:", "--diff-filter=AM", "HEAD"])
-            .output()
-            .expect("Failed to get git show --diff-filter=AM");
-        print_verbose("Run 'git show --pretty=format: --diff-filter=AM HEAD'");
-
-        // Add file status information (including deleted files)
-        let status_info = String::from_utf8_lossy(&show_status_output.stdout);
-        diff_content.push_str(&status_info);
-        diff_content.push('\n');
-
-        // Add full diff content only for added/modified files
-        if !show_diff_output.stdout.is_empty() {
-            diff_content.push_str("\nDetailed changes for added/modified files in last commit (excluding deleted files):\n");
-            diff_content.push_str(&String::from_utf8_lossy(&show_diff_output.stdout));
-            diff_content.push('\n');
-        }
-        println!("As '-p' option is enabled, I will amend the last commit message");
+        if cli.dry_run {
+            eprintln!("As '-p' option is enabled, the message is generated for the last commit");
+        } else {
+            println!("As '-p' option is enabled, I will amend the last commit message");
+        }
     }
-    if diff_content.is_empty() {
-        println!("No changes found. To override last commit message, please use '-p' option");
+    if changes_to_describe.is_empty() {
+        print_progress(
+            cli.dry_run,
+            "No changes found. To override last commit message, please use '-p' option",
+        );
         return;
     }
 
-    let diff_limit = crate::cli::custom_param::get_lines_limit();
-    if diff_content.lines().count() > diff_limit {
-        eprintdoc!(
-            r"
-            Your changed lines count ({}) exceeds the limit: {}. 
-            Please use 'git commit' to commit the changes or adjust the limit by 'gim config --change-limit <LIMIT>' and try again.
-            ",
-            diff_content.lines().count(),
-            diff_limit
+    // The hard stop is opt-in, large changes are fitted into the token budget otherwise
+    if let Some(diff_limit) = custom_param::get_lines_limit() {
+        let (_, changed_lines) = diff::numstat(cli.overwrite);
+        if changed_lines > diff_limit {
+            eprintdoc!(
+                r"
+                Your changed lines count ({}) exceeds the limit: {}. 
+                Please use 'git commit' to commit the changes or adjust the limit by 'gim config --lines-limit <LIMIT>' and try again.
+                ",
+                changed_lines,
+                diff_limit
+            );
+            std::process::exit(1);
+        }
+    }
+
+    // Nothing leaves the machine before the secrets are redacted
+    if let Err(report) = secret::protect(&mut changes_to_describe) {
+        ai_generating_error(
+            &format!("Error: {}", report),
+            auto_add && !changes.is_empty(),
         );
         std::process::exit(1);
     }
 
-    let config_result = get_validated_ai_config(cli.auto_add, !changes.is_empty());
+    let config_result =
+        get_validated_ai_config(auto_add, !changes.is_empty(), cli.profile.as_deref());
     if config_result.is_none() {
         return;
     }
-    let (url, model_name, api_key, language) = config_result.unwrap();
+    let ai = config_result.unwrap();
 
-    if language != "English" {
-        diff_content.push_str(&format!(
-            "\n The answer should be in {} language. If you cannot recognize this language, use English instead.",
-            language
-        ));
-    }
-    let system = get_diff_prompt();
-    let res = chat(
-        url.clone(),
-        model_name.clone(),
-        api_key.clone(),
-        Some(system),
-        diff_content.clone(),
-        cli.verbose,
-    )
-    .await;
-    if let Err(e) = res {
-        ai_generating_error(
-            &format!("Error: {}", e),
-            cli.auto_add && !changes.is_empty(),
-        );
-        return;
-    }
-    let file_changes = res.unwrap();
+    let trailers = match Trailers::collect(cli) {
+        Ok(trailers) => trailers,
+        Err(e) => {
+            ai_generating_error(&format!("Error: {}", e), auto_add && !changes.is_empty());
+            return;
+        }
+    };
 
-    let mut commit_subject = cli.title.clone();
-    if commit_subject.is_none() {
-        let system = get_subject_prompt();
-        let res = chat(
-            url,
-            model_name,
-            api_key,
-            Some(system),
-            format!("The changes are: \n{}", file_changes),
+    let context = PromptContext::collect(&ai.language, cli.overwrite);
+    let mut hint: Option<String> = None;
+    let (commit_subject, file_changes) = loop {
+        let res = generate_message(
+            &ai,
+            &changes_to_describe,
+            &context,
+            cli.title.as_deref(),
+            hint.as_deref(),
             cli.verbose,
         )
         .await;
-
-        match res {
-            Ok(answer) => {
-                commit_subject = Some(answer);
+        let (mut subject, mut message) = match res {
+            Ok(generated) => generated,
+            Err(e) => {
+                ai_generating_error(
+                    &format!("Error: {}", e),
+                    auto_add && !changes.is_empty(),
+                );
+                return;
             }
+        };
+        // Trailers are added before the review, so that they can be edited too
+        message = match trailers.apply(&subject, &message) {
+            Ok(message) => message,
             Err(e) => {
-                commit_subject = Some(format!("Error: {}", e));
+                ai_generating_error(
+                    &format!("Error: failed to add the trailers - {}", e),
+                    auto_add && !changes.is_empty(),
+                );
+                return;
             }
+        };
+        if cli.dry_run {
+            // Print only the message to stdout so that it can be piped into other tools
+            println!("{}\n\n{}", subject, message);
+            return;
+        }
+        if cli.yes || !std::io::stdin().is_terminal() {
+            print_commit_message(&subject, &message);
+            break (subject, message);
         }
-    }
-    let commit_subject = commit_subject.unwrap();
-    print_verbose(&format!("AI chat content: {}", diff_content));
-    println!();
-    printdoc!(
-        r#"
-        >>>>>>>>>>>>>>>>>>>>>>>>>
-        Commit subject: "{}"
 
-        Commit message: "{}"
-        <<<<<<<<<<<<<<<<<<<<<<<<<
-        "#,
-        commit_subject,
-        file_changes
-    );
+        // Edit the message in place until the user decides what to do with it
+        let action = loop {
+            print_commit_message(&subject, &message);
+            match review::ask_review_action() {
+                ReviewAction::Edit => match review::edit_message(&subject, &message) {
+                    Ok(edited) => (subject, message) = edited,
+                    Err(e) => eprintln!("Error: failed to edit commit message - {}", e),
+                },
+                action => break action,
+            }
+        };
+        match action {
+            ReviewAction::Regenerate(new_hint) => hint = new_hint,
+            ReviewAction::Abort => {
+                // The index is left as it was before '-a' staged the changes
+                let restored = saved_index.as_ref().is_some_and(|tree| {
+                    print_verbose(&format!("Run 'git read-tree {}'", tree));
+                    Command::new("git")
+                        .args(["read-tree", tree])
+                        .output()
+                        .is_ok_and(|output| output.status.success())
+                });
+                ai_generating_error(
+                    "Commit aborted, nothing was committed",
+                    auto_add && !changes.is_empty() && !restored,
+                );
+                return;
+            }
+            _ => break (subject, message),
+        }
+    };
 
     // Prepare commit message
     let mut commit_args = vec!["commit"];
@@ -404,13 +535,36 @@ fn handle_prompt_command(
     edit: bool,
     prompt: Option<&str>,
     editor: Option<&str>,
+    render: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let config_dir = directory::config_dir()?;
     let diff_prompt_path = config_dir.join(DIFF_PROMPT_FILE);
     let subject_prompt_path = config_dir.join(SUBJECT_PROMPT_FILE);
 
-    let diff_prompt = get_diff_prompt();
-    let subject_prompt = get_subject_prompt();
+    let mut diff_prompt = get_diff_prompt();
+    let mut subject_prompt = get_subject_prompt();
+    if render {
+        if !diff::is_git_repository() {
+            return Err("should run in a git repository to render the prompts".into());
+        }
+        let language = super::ai_configer::get_ai_config()
+            .ok()
+            .and_then(|ai| ai.get("language").and_then(|v| v.as_str()).map(String::from))
+            .unwrap_or_else(|| "English".to_string());
+        let context = PromptContext::collect(&language, false);
+        diff_prompt = template::render(&diff_prompt, &context);
+        subject_prompt = template::render(&subject_prompt, &context);
+    }
+
+    // The prompt files of the repository take precedence over the global ones
+    for file_name in [DIFF_PROMPT_FILE, SUBJECT_PROMPT_FILE] {
+        if let Some(path) = repo_prompt_path(file_name) {
+            println!(
+                "Note: this repository uses its own prompt file '{}'",
+                path.display()
+            );
+        }
+    }
 
     if edit {
         if let Some(prompt_type) = prompt {
@@ -520,6 +674,243 @@ fn open_config_directory() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Asks the AI for the commit message of the given changes.
+///
+/// The file changes summary is generated first and then used to generate the subject,
+/// unless a title was given by the user.
+///
+/// # Arguments
+///
+/// * `ai` - The AI configuration.
+/// * `changes` - The collected changes sent to the AI, summarized concurrently when they
+///   were split into several chunks or files.
+/// * `context` - The repository context rendered into the prompt templates.
+/// * `title` - Optional commit subject provided by the user.
+/// * `hint` - Optional extra instruction for the AI when regenerating.
+/// * `verbose` - Whether to print verbose log information.
+///
+/// # Returns
+///
+/// * `Ok((String, String))` containing the commit subject and the commit message. The subject
+///   starts with [`SUBJECT_ERROR_PREFIX`] when it could not be generated.
+/// * `Err(Box<dyn Error>)` if the file changes summary could not be generated.
+pub async fn generate_message(
+    ai: &AiConfig,
+    changes: &Changes,
+    context: &PromptContext,
+    title: Option<&str>,
+    hint: Option<&str>,
+    verbose: bool,
+) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let diff_prompt = get_diff_prompt();
+    let mut instructions = String::new();
+    // Templates using the language variable decide where the language goes themselves
+    if ai.language != "English" && !template::uses_variable(&diff_prompt, "language") {
+        instructions.push_str(&format!(
+            "\n The answer should be in {} language. If you cannot recognize this language, use English instead.",
+            ai.language
+        ));
+    }
+    if let Changes::Chunks(chunks) = changes {
+        print_verbose(&format!(
+            "AI chat content: {}{}",
+            chunks.join("\n"),
+            instructions
+        ));
+    }
+    let hint = hint
+        .map(|hint| format!("\nAdditional instruction: {}", hint))
+        .unwrap_or_default();
+    instructions.push_str(&hint);
+
+    let profiles = ai.profiles();
+    let system = template::render(&diff_prompt, context);
+    let (file_changes, mut used) = match changes {
+        Changes::Chunks(chunks) if chunks.len() == 1 => {
+            let diff_content = &chunks[0];
+            chat_with_fallback(
+                &profiles,
+                Some(system),
+                format!("{}{}", diff_content, instructions),
+                verbose,
+            )
+            .await?
+        }
+        Changes::Chunks(chunks) => {
+            let summary =
+                summarize::summarize_chunks(ai, &system, chunks, &instructions, verbose).await?;
+            (summary, 0)
+        }
+        Changes::Files(files) => {
+            let summary =
+                summarize::summarize_files(ai, &system, files, &instructions, verbose).await?;
+            (summary, 0)
+        }
+    };
+
+    // The rules of the repository, such as its commitlint config, are added to the prompt
+    let lint_rules = lint::load_rules();
+    let rules = lint_rules.clone().unwrap_or_default();
+    let commit_subject = match title {
+        Some(title) => {
+            if lint_rules.is_some()
+                && let Err(e) = conventional::validate(title, &rules)
+            {
+                eprintln!("Warning: the subject '{}' breaks the commit rules: {}", title, e);
+            }
+            title.to_string()
+        }
+        None => {
+            // Profiles that already failed are not asked again for the subject
+            let mut system = template::render(&get_subject_prompt(), context);
+            if let Some(lint_rules) = &lint_rules {
+                system.push_str(&format!("\n{}", lint_rules.describe()));
+            }
+            // The scope inferred from the changed paths wins over the one chosen by the AI
+            let scope = Some(context.scope.as_str())
+                .filter(|scope| !scope.is_empty() && !rules.scope_forbidden)
+                .filter(|scope| rules.scopes.is_empty() || rules.scopes.iter().any(|s| s == scope));
+            if let Some(scope) = scope {
+                system.push_str(&format!("\nUse '{}' as the scope of the subject.", scope));
+            }
+            let breaking = !context.breaking_changes.is_empty();
+            if breaking {
+                let findings: Vec<String> =
+                    context.breaking_changes.iter().map(|f| f.to_string()).collect();
+                system.push_str(&format!(
+                    "\nThe changes break the public API: {}. Mark the subject as a breaking change with '!', such as 'feat!: description'.",
+                    findings.join("; ")
+                ));
+            }
+            let user = format!("The changes are: \n{}{}", file_changes, hint);
+            let res = generate_subject(
+                &profiles[used..],
+                system,
+                user,
+                &rules,
+                scope,
+                breaking,
+                verbose,
+            )
+            .await;
+            match res {
+                Ok((subject, index)) => {
+                    used += index;
+                    subject
+                }
+                Err(e) => format!("{}{}", SUBJECT_ERROR_PREFIX, e),
+            }
+        }
+    };
+    let file_changes = match rules.body_max_line_length {
+        Some(max_line_length) => conventional::wrap_body(&file_changes, max_line_length),
+        None => file_changes,
+    };
+    let file_changes = if context.breaking_changes.is_empty() {
+        file_changes
+    } else {
+        format!(
+            "{}\n\n{}",
+            file_changes.trim_end(),
+            breaking::footer(&context.breaking_changes)
+        )
+    };
+    // The ticket ids are added by gim rather than by the AI, which is told not to add any
+    let (commit_subject, file_changes) =
+        ticket::add_references(commit_subject, file_changes, &context.tickets);
+    if profiles.len() > 1 {
+        eprintln!("Message generated by model '{}'", profiles[used].model);
+    }
+    Ok((commit_subject, file_changes))
+}
+
+/// Asks the AI for the commit subject, repairs its answer and checks it against Conventional
+/// Commits and the commit rules, asking again with the error until it is valid or the attempts
+/// are used up.
+///
+/// The scope inferred from the changed paths, if any, is set on the subject before it is
+/// checked, and the subject is marked with `!` when the changes break the public API.
+///
+/// # Returns
+///
+/// * `Ok((String, usize))` containing the subject and the index of the profile that answered.
+///   The last subject is kept, with a warning, when none is valid.
+/// * `Err(Box<dyn Error>)` if no profile answered.
+async fn generate_subject(
+    profiles: &[&AiConfig],
+    system: String,
+    user: String,
+    rules: &SubjectRules,
+    scope: Option<&str>,
+    breaking: bool,
+    verbose: bool,
+) -> Result<(String, usize), Box<dyn std::error::Error>> {
+    let attempts = custom_param::get_subject_attempts();
+    let mut used = 0;
+    let mut request = user.clone();
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let (answer, index) =
+            chat_with_fallback(&profiles[used..], Some(system.clone()), request, verbose).await?;
+        used += index;
+        let mut subject = conventional::repair(&answer, rules);
+        // Subjects in another format, such as asked by a custom prompt, are left as they are
+        if conventional::has_type(&subject, rules) {
+            if let Some(scope) = scope {
+                subject = conventional::with_scope(&subject, scope);
+            }
+            if breaking {
+                subject = conventional::mark_breaking(&subject);
+            }
+        }
+        // Without attempts the subject is only repaired, not checked
+        if attempts == 0 {
+            return Ok((subject, used));
+        }
+        let error = match conventional::validate(&subject, rules) {
+            Ok(valid) => return Ok((valid.to_string(), used)),
+            Err(error) => error,
+        };
+        if attempt >= attempts {
+            eprintln!(
+                "Warning: the subject '{}' breaks the commit rules: {}",
+                subject, error
+            );
+            return Ok((subject, used));
+        }
+        eprintln!("Subject '{}' rejected: {}, asking again...", subject, error);
+        request = format!(
+            "{}\n\nYour previous answer was '{}', which is invalid: {}. Answer again with only the fixed subject.",
+            user, subject, error
+        );
+    }
+}
+
+fn print_commit_message(subject: &str, message: &str) {
+    println!();
+    printdoc!(
+        r#"
+        >>>>>>>>>>>>>>>>>>>>>>>>>
+        Commit subject: "{}"
+
+        Commit message: "{}"
+        <<<<<<<<<<<<<<<<<<<<<<<<<
+        "#,
+        subject,
+        message
+    );
+}
+
+fn print_progress(dry_run: bool, message: &str) {
+    // Keep stdout clean for the generated message in dry-run mode
+    if dry_run {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
+}
+
 fn ai_generating_error(abort: &str, auto_add: bool) {
     eprintln!("{}", abort);
     if auto_add {
@@ -527,10 +918,23 @@ fn ai_generating_error(abort: &str, auto_add: bool) {
     }
 }
 
-fn get_validated_ai_config(
+/// Reads the AI configuration and reports the missing fields.
+///
+/// # Arguments
+///
+/// * `auto_add` - Whether the changes were staged by gim, used in the error report.
+/// * `changed` - Whether there are changes in the work tree, used in the error report.
+/// * `profile` - Optional ai profile overriding the active one.
+///
+/// # Returns
+///
+/// * `Some(AiConfig)` if the configuration is complete.
+/// * `None` if a field is missing, after printing the error.
+pub fn get_validated_ai_config(
     auto_add: bool,
     changed: bool,
-) -> Option<(String, String, String, String)> {
+    profile: Option<&str>,
+) -> Option<AiConfig> {
     let ai_config = super::ai_configer::get_ai_config();
     if ai_config.is_err() {
         ai_generating_error(
@@ -550,6 +954,14 @@ fn get_validated_ai_config(
         }
     };
 
+    let ai_config = match ai_profile::apply_profile(&ai_config, profile) {
+        Ok(config) => config,
+        Err(e) => {
+            ai_generating_error(&format!("Error: {}", e), auto_add && changed);
+            return None;
+        }
+    };
+
     let url = match ai_config.get("url").and_then(|v| v.as_str()) {
         Some(v) => v,
         None => {
@@ -564,13 +976,11 @@ fn get_validated_ai_config(
             return None;
         }
     };
-    let api_key = match ai_config.get("apikey").and_then(|v| v.as_str()) {
-        Some(v) => v,
-        None => {
-            ai_generating_error("Error: Missing 'apikey' in AI config", auto_add && changed);
-            return None;
-        }
-    };
+    let provider = ai_config
+        .get("provider")
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_string());
+    let api_key = ai_config.get("apikey").and_then(|v| v.as_str());
     let language = match ai_config.get("language").and_then(|v| v.as_str()) {
         Some(v) => v,
         None => {
@@ -582,12 +992,40 @@ fn get_validated_ai_config(
         }
     };
 
-    Some((
-        url.to_string(),
-        model_name.to_string(),
-        api_key.to_string(),
-        language.to_string(),
-    ))
+    let stream = ai_config
+        .get("stream")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let integer = |key: &str, default: u64| {
+        ai_config
+            .get(key)
+            .and_then(|v| v.as_integer())
+            .and_then(|v| u64::try_from(v).ok())
+            .unwrap_or(default)
+    };
+
+    let mut ai = AiConfig {
+        url: url.to_string(),
+        model: model_name.to_string(),
+        apikey: api_key.unwrap_or_default().to_string(),
+        language: language.to_string(),
+        provider,
+        stream,
+        connect_timeout_secs: integer("connect_timeout_secs", DEFAULT_CONNECT_TIMEOUT_SECS),
+        read_timeout_secs: integer("read_timeout_secs", DEFAULT_READ_TIMEOUT_SECS),
+        max_retries: integer("max_retries", DEFAULT_MAX_RETRIES as u64) as u32,
+        fallbacks: Vec::new(),
+    };
+    // Local providers, such as Ollama or a llama.cpp server, don't need an api key
+    if api_key.is_none()
+        && !is_local_url(url)
+        && !registry::resolve(&ai).is_ok_and(|provider| provider.is_local())
+    {
+        ai_generating_error("Error: Missing 'apikey' in AI config", auto_add && changed);
+        return None;
+    }
+    ai.fallbacks = super::ai_configer::get_fallback_configs(&ai_config, &ai);
+    Some(ai)
 }
 
 #[cfg(test)]
@@ -605,6 +1043,14 @@ mod tests {
             overwrite: true,
             title: None,
             verbose: true,
+            yes: true,
+            dry_run: false,
+            profile: None,
+            signoff: false,
+            co_author: Vec::new(),
+            reviewed_by: Vec::new(),
+            breaking: None,
+            trailer: Vec::new(),
         };
         run_cli(&cli, config).await;
     }
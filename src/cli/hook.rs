@@ -0,0 +1,189 @@
+use std::{fs, path::PathBuf, process::Command};
+
+use indoc::formatdoc;
+
+use crate::cli::{
+    custom_param::{get_diff_strategy, get_lines_limit, get_token_budget},
+    diff,
+    entry::{SUBJECT_ERROR_PREFIX, generate_message, get_validated_ai_config},
+    secret,
+    template::PromptContext,
+    trailer::Trailers,
+    verbose::print_verbose,
+};
+
+const HOOK_NAME: &str = "prepare-commit-msg";
+const HOOK_MARKER: &str = "# Installed by gim (git-intelligence-message)";
+
+/// Commit message sources, as passed by git, for which no message is generated:
+/// `-m`/`-F` messages, merges, squashes and reused or amended commits.
+const SKIPPED_SOURCES: [&str; 4] = ["message", "merge", "squash", "commit"];
+
+/// Installs the `prepare-commit-msg` hook into the current repository.
+///
+/// # Arguments
+///
+/// * `force` - Overwrite an existing hook that was not installed by gim.
+///
+/// # Returns
+///
+/// * `Ok(())` if the hook was installed.
+/// * `Err(Box<dyn Error>)` if the hook path cannot be resolved or written.
+pub fn install_hook(force: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let path = hook_path()?;
+    if path.exists() && !is_gim_hook(&path) && !force {
+        return Err(format!(
+            "a {} hook already exists at '{}', use '--force' to overwrite it",
+            HOOK_NAME,
+            path.display()
+        )
+        .into());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let script = formatdoc!(
+        r#"
+        #!/bin/sh
+        {}
+        command -v gim >/dev/null 2>&1 || exit 0
+        exec gim hook run "$@"
+        "#,
+        HOOK_MARKER
+    );
+    fs::write(&path, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+    println!("✅ Installed {} hook at '{}'", HOOK_NAME, path.display());
+    Ok(())
+}
+
+/// Removes the `prepare-commit-msg` hook installed by gim from the current repository.
+///
+/// Hooks that were not installed by gim are left untouched.
+///
+/// # Returns
+///
+/// * `Ok(())` if the hook was removed or no hook was installed.
+/// * `Err(Box<dyn Error>)` if the hook belongs to another tool or cannot be removed.
+pub fn uninstall_hook() -> Result<(), Box<dyn std::error::Error>> {
+    let path = hook_path()?;
+    if !path.exists() {
+        println!("No {} hook installed", HOOK_NAME);
+        return Ok(());
+    }
+    if !is_gim_hook(&path) {
+        return Err(format!(
+            "the {} hook at '{}' was not installed by gim",
+            HOOK_NAME,
+            path.display()
+        )
+        .into());
+    }
+    fs::remove_file(&path)?;
+    println!("✅ Removed {} hook at '{}'", HOOK_NAME, path.display());
+    Ok(())
+}
+
+/// Runs as the `prepare-commit-msg` hook: generates the commit message of the staged
+/// changes and writes it into the message file passed by git.
+///
+/// The trailers of the `[trailers]` config section are added as by `gim`, the flags of `gim`
+/// such as `--signoff` are left to `git commit`.
+///
+/// Failures are reported but never block the commit, the message file is left as it is instead.
+///
+/// # Arguments
+///
+/// * `message_file` - The commit message file passed by git.
+/// * `source` - The source of the commit message passed by git, if any.
+/// * `verbose` - Whether to print verbose log information.
+pub async fn run_hook(message_file: &str, source: Option<&str>, verbose: bool) {
+    if let Some(source) = source
+        && SKIPPED_SOURCES.contains(&source)
+    {
+        print_verbose(&format!("Skip generating message for '{}' commit", source));
+        return;
+    }
+
+    let mut changes = diff::collect_changes(get_diff_strategy(), get_token_budget(), true, false);
+    if changes.is_empty() {
+        return;
+    }
+    if let Some(diff_limit) = get_lines_limit() {
+        let (_, changed_lines) = diff::numstat(false);
+        if changed_lines > diff_limit {
+            eprintln!(
+                "gim: changed lines count ({}) exceeds the limit: {}, skip generating message",
+                changed_lines, diff_limit
+            );
+            return;
+        }
+    }
+
+    if let Err(report) = secret::protect(&mut changes) {
+        eprintln!("gim: {}, skip generating message", report);
+        return;
+    }
+
+    let Some(ai) = get_validated_ai_config(false, false, None) else {
+        return;
+    };
+    let trailers = match Trailers::from_config() {
+        Ok(trailers) => trailers,
+        Err(e) => {
+            eprintln!("gim: {}, skip generating message", e);
+            return;
+        }
+    };
+    eprintln!("gim: generating commit message...");
+    let context = PromptContext::collect(&ai.language, false);
+    let generated = generate_message(&ai, &changes, &context, None, None, verbose).await;
+    let (subject, message) = match generated {
+        Ok(generated) => generated,
+        Err(e) => {
+            eprintln!("gim: failed to generate commit message - {}", e);
+            return;
+        }
+    };
+    // A failed subject must not end up in the commit, with `--no-edit` for instance
+    if let Some(e) = subject.strip_prefix(SUBJECT_ERROR_PREFIX) {
+        eprintln!("gim: failed to generate commit subject - {}", e);
+        return;
+    }
+    let message = match trailers.apply(&subject, &message) {
+        Ok(message) => message,
+        Err(e) => {
+            eprintln!("gim: failed to add the trailers - {}", e);
+            return;
+        }
+    };
+
+    // Keep what git already put in the file, such as the template and the comments
+    let existing = fs::read_to_string(message_file).unwrap_or_default();
+    let content = format!("{}\n\n{}\n{}", subject.trim(), message.trim(), existing);
+    if let Err(e) = fs::write(message_file, content) {
+        eprintln!("gim: failed to write commit message file - {}", e);
+    }
+}
+
+fn hook_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    // --git-path takes core.hooksPath into account
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", &format!("hooks/{}", HOOK_NAME)])
+        .output()?;
+    if !output.status.success() {
+        return Err("should run in a git repository".into());
+    }
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim(),
+    ))
+}
+
+fn is_gim_hook(path: &PathBuf) -> bool {
+    fs::read_to_string(path).is_ok_and(|content| content.contains(HOOK_MARKER))
+}
@@ -1,68 +1,28 @@
-use serde::{Deserialize, Serialize};
-use std::error::Error;
+use std::{
+    error::Error,
+    io::{self, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-#[derive(Serialize, Deserialize, Debug, validator::Validate)]
-struct Message {
-    #[validate(length(min = 1))]
-    role: String,
-    #[validate(length(min = 1))]
-    content: String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Request {
-    model: String,
-    messages: Vec<Message>,
-    temperature: f32,
-    stream: bool,
-    extra_body: RequestExtraBody,
-}
-
-impl Default for Request {
-    fn default() -> Self {
-        let empty_string = String::new();
-        Self {
-            model: empty_string,
-            messages: Default::default(),
-            temperature: 0.3,
-            stream: false,
-            extra_body: RequestExtraBody {
-                enable_thinking: false,
-            },
-        }
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct RequestExtraBody {
-    enable_thinking: bool,
-}
+use crate::cli::{
+    ai_configer::AiConfig,
+    provider::{Provider, StreamEvent, registry},
+};
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Response {
-    choices: Option<Vec<Choice>>,
-    error: Option<ResponseError>,
-}
+const RETRY_BASE_DELAY_MILLIS: u64 = 1000;
+const MAX_RETRY_DELAY_SECS: u64 = 60;
+const MAX_ERROR_BODY_CHARS: usize = 500;
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Choice {
-    message: Message,
-    finish_reason: String,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct ResponseError {
-    message: String,
-    r#type: Option<String>,
-}
-
-/// Sends a chat request to the specified AI API endpoint and returns the response.
+/// Sends a chat request to the configured AI provider and returns the response.
+///
+/// When streaming is enabled the answer is rendered to stderr token by token as it arrives.
+/// Providers rejecting streaming requests are asked again without streaming.
+/// Network errors, timeouts, `429` and `5xx` answers are retried with an exponential backoff,
+/// honoring the `Retry-After` header.
 ///
 /// # Arguments
 ///
-/// * `url` - The API endpoint URL.
-/// * `model_name` - The name of the AI model to use.
-/// * `api_key` - The API key for authentication.
+/// * `ai` - The AI configuration: url, model, api key, provider, streaming, timeouts and retries.
 /// * `system` - Optional system prompt.
 /// * `user` - The user input or prompt.
 /// * `log_info` - Whether to print verbose log information.
@@ -72,74 +32,243 @@ struct ResponseError {
 /// * `Ok(String)` containing the AI response if successful.
 /// * `Err(Box<dyn Error>)` if the request fails or the response is invalid.
 pub async fn chat(
-    url: String,
-    model_name: String,
-    api_key: String,
+    ai: &AiConfig,
     system: Option<String>,
     user: String,
     log_info: bool,
 ) -> Result<String, Box<dyn Error>> {
-    let mut request_body = Request {
-        model: model_name,
-        messages: vec![Message {
-            role: "user".to_string(),
-            content: user,
-        }],
-        ..Default::default()
+    let provider = registry::resolve(ai)?;
+    let provider = provider.as_ref();
+    let mut stream = ai.stream;
+
+    // If user provided a base URL, the full URL is constructed with the provider path
+    let Some(url) = provider.endpoint(&ai.url) else {
+        return Err(format!("please setup ai url first for model '{}'", ai.model).into());
     };
-    if let Some(system) = system {
-        request_body.messages.push(Message {
-            role: "system".to_string(),
-            content: system,
-        });
+
+    if log_info {
+        eprintln!("ai request url: {} (provider: {})", url, provider.name());
     }
-    // let url = check_url(&url, &request_body.model);
-    let mut url = url;
-    if !url.starts_with("http") {
-        if let Some(str) = get_url_by_model(&request_body.model) {
-            url = str;
-        } else {
-            eprintln!("Error: please setup ai url first");
-            std::process::exit(1);
+
+    // 发送请求
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(ai.connect_timeout_secs))
+        .build()?;
+    let read_timeout = Duration::from_secs(ai.read_timeout_secs);
+    let mut attempt: u32 = 0;
+    let mut response = loop {
+        let request_body =
+            provider.request_body(&ai.model, system.clone(), user.clone(), stream)?;
+        let response = match send_request(
+            &client,
+            provider,
+            &url,
+            &ai.apikey,
+            &request_body,
+            read_timeout,
+        )
+        .await
+        {
+            Ok(response) => response,
+            Err(e) if attempt < ai.max_retries => {
+                attempt += 1;
+                wait_before_retry(&e.to_string(), backoff_delay(attempt), attempt, ai.max_retries)
+                    .await;
+                continue;
+            }
+            Err(e) => {
+                return Err(format!("ai request failed after {} attempts: {}", attempt + 1, e).into());
+            }
+        };
+
+        let status = response.status();
+        if status.is_success() {
+            break response;
+        }
+        if stream && (status.as_u16() == 400 || status.as_u16() == 422) {
+            // Some providers reject streaming requests, fall back to a plain request
+            if log_info {
+                eprintln!("ai streaming request rejected ({}), retry without streaming", status);
+            }
+            stream = false;
+            continue;
+        }
+
+        let retry_after = retry_after(&response);
+        let body = with_timeout(read_timeout, response.text())
+            .await
+            .unwrap_or_default();
+        let error = format!("ai request failed: {} - {}", status, error_detail(provider, &body));
+        let retryable = status.as_u16() == 429 || status.is_server_error();
+        if !retryable || attempt >= ai.max_retries {
+            return Err(error.into());
         }
-    } else {
-        // If user provided a base URL, construct the full URL
-        url = construct_full_url(&url);
+        attempt += 1;
+        let delay = retry_after.unwrap_or_else(|| backoff_delay(attempt));
+        wait_before_retry(&error, delay, attempt, ai.max_retries).await;
+    };
+    let status = response.status();
+
+    // Server-sent events, or a JSON object per line for Ollama
+    let is_event_stream = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            v.starts_with("text/event-stream") || v.starts_with("application/x-ndjson")
+        });
+    if stream && is_event_stream {
+        let answer = read_stream(provider, &mut response, read_timeout).await?;
+        if log_info {
+            eprintln!("ai request result ({}): {}", status, answer);
+        }
+        return Ok(answer);
     }
 
+    let res_text = with_timeout(read_timeout, response.text()).await?;
     if log_info {
-        println!("ai request url: {}", url);
+        eprintln!("ai request result ({}): {}", status, res_text);
     }
+    provider.parse_response(&res_text)
+}
 
-    // 发送请求
-    let client = reqwest::Client::new();
-    let response = client
+/// Sends a chat request to the given profiles in order, until one of them answers.
+///
+/// # Arguments
+///
+/// * `profiles` - The AI profiles to try, the primary one first.
+/// * `system` - Optional system prompt.
+/// * `user` - The user input or prompt.
+/// * `log_info` - Whether to print verbose log information.
+///
+/// # Returns
+///
+/// * `Ok((String, usize))` containing the AI response and the index of the profile that answered.
+/// * `Err(Box<dyn Error>)` containing the error of the last profile if all of them fail.
+pub async fn chat_with_fallback(
+    profiles: &[&AiConfig],
+    system: Option<String>,
+    user: String,
+    log_info: bool,
+) -> Result<(String, usize), Box<dyn Error>> {
+    let mut last_error: Box<dyn Error> = "no ai profile configured".into();
+    for (index, ai) in profiles.iter().enumerate() {
+        match chat(ai, system.clone(), user.clone(), log_info).await {
+            Ok(answer) => return Ok((answer, index)),
+            Err(e) => {
+                if let Some(next) = profiles.get(index + 1) {
+                    eprintln!(
+                        "Warning: model '{}' failed - {}, falling back to model '{}'",
+                        ai.model, e, next.model
+                    );
+                }
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+async fn send_request(
+    client: &reqwest::Client,
+    provider: &dyn Provider,
+    url: &str,
+    api_key: &str,
+    request_body: &serde_json::Value,
+    read_timeout: Duration,
+) -> Result<reqwest::Response, Box<dyn Error>> {
+    let request = client
         .post(url)
-        .header("Authorization", format!("Bearer {}", &api_key))
         .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await?;
-    let status = response.status();
-    if status.as_u16() >= 400 {
-        return Err(format!("ai request failed: {}", status).into());
-    }
+        .json(request_body);
+    with_timeout(read_timeout, provider.authorize(request, api_key).send()).await
+}
 
-    let res_text = response.text().await?;
-    if log_info {
-        println!("ai request result ({}): {}", status, res_text);
+/// Awaits a response future, failing when nothing is received within the read timeout.
+async fn with_timeout<T>(
+    read_timeout: Duration,
+    future: impl Future<Output = Result<T, reqwest::Error>>,
+) -> Result<T, Box<dyn Error>> {
+    match tokio::time::timeout(read_timeout, future).await {
+        Ok(result) => Ok(result?),
+        Err(_) => Err(format!("no response within {} seconds", read_timeout.as_secs()).into()),
     }
+}
 
-    let res: Response = serde_json::from_str(&res_text)?;
+/// Reads a streaming response, rendering the content to stderr as it arrives.
+async fn read_stream(
+    provider: &dyn Provider,
+    response: &mut reqwest::Response,
+    read_timeout: Duration,
+) -> Result<String, Box<dyn Error>> {
+    let mut answer = String::new();
+    // Buffer raw bytes, as a chunk may end in the middle of a multi-byte character
+    let mut buffer: Vec<u8> = Vec::new();
+    'stream: while let Some(chunk) = with_timeout(read_timeout, response.chunk()).await? {
+        buffer.extend_from_slice(&chunk);
+        while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            match provider.parse_stream_line(&String::from_utf8_lossy(&line))? {
+                Some(StreamEvent::Content(content)) => {
+                    eprint!("{}", content);
+                    let _ = io::stderr().flush();
+                    answer.push_str(&content);
+                }
+                Some(StreamEvent::Done) => break 'stream,
+                None => {}
+            }
+        }
+    }
+    eprintln!();
+    Ok(answer)
+}
 
-    if let Some(res) = res.choices {
-        return Ok(res[0].message.content.clone());
+/// Returns the error message of a failed response: the provider error message if the body
+/// is a JSON error, otherwise the beginning of the body.
+fn error_detail(provider: &dyn Provider, body: &str) -> String {
+    if serde_json::from_str::<serde_json::Value>(body).is_ok()
+        && let Err(e) = provider.parse_response(body)
+    {
+        return e.to_string();
     }
-    eprintln!("{:?}", res);
-    if let Some(res) = res.error {
-        return Err(res.message.into());
+    let body = body.trim();
+    if body.is_empty() {
+        return "empty response body".to_string();
     }
-    Err("unkown exception".into())
+    body.chars().take(MAX_ERROR_BODY_CHARS).collect()
+}
+
+/// Reads the `Retry-After` header, in seconds.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|secs| Duration::from_secs(secs.min(MAX_RETRY_DELAY_SECS)))
+}
+
+/// Returns the exponential backoff delay of the given retry attempt, with a random jitter.
+fn backoff_delay(attempt: u32) -> Duration {
+    let base = RETRY_BASE_DELAY_MILLIS.saturating_mul(1 << attempt.saturating_sub(1).min(10));
+    let base = base.min(MAX_RETRY_DELAY_SECS * 1000);
+    // Jitter up to half the delay, so that concurrent clients don't retry at the same time
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or_default();
+    Duration::from_millis(base + nanos % (base / 2 + 1))
+}
+
+async fn wait_before_retry(error: &str, delay: Duration, attempt: u32, max_retries: u32) {
+    eprintln!(
+        "Warning: {}, retry in {:.1}s ({}/{})",
+        error,
+        delay.as_secs_f32(),
+        attempt,
+        max_retries
+    );
+    tokio::time::sleep(delay).await;
 }
 
 /// Returns the default API URL for the given model name, if recognized.
@@ -153,80 +282,100 @@ pub async fn chat(
 /// * `Some(String)` containing the default URL if the model is recognized.
 /// * `None` if the model is not recognized.
 pub fn get_url_by_model(model_name: &str) -> Option<String> {
-    if model_name.starts_with("moonshot") {
-        return Some(format!("{}{}", crate::constants::MOONSHOT_BASE_URL, crate::constants::DEFAULT_CHAT_COMPLETIONS_PATH));
-    }
-    if model_name.starts_with("qwen") {
-        return Some(format!("{}{}", crate::constants::QWEN_BASE_URL, crate::constants::QWEN_CHAT_COMPLETIONS_PATH));
-    }
-    if model_name.starts_with("gpt") {
-        return Some(format!("{}{}", crate::constants::GPT_BASE_URL, crate::constants::DEFAULT_CHAT_COMPLETIONS_PATH));
-    }
-    if model_name.starts_with("gemini") {
-        return Some(format!("{}{}", crate::constants::GEMINI_BASE_URL, crate::constants::GEMINI_CHAT_COMPLETIONS_PATH));
-    }
-    if model_name.starts_with("doubao") {
-        return Some(format!("{}{}", crate::constants::DOUBAO_BASE_URL, crate::constants::DOUBAO_CHAT_COMPLETIONS_PATH));
-    }
-    if model_name.starts_with("glm") {
-        return Some(format!("{}{}", crate::constants::GLM_BASE_URL, crate::constants::GLM_CHAT_COMPLETIONS_PATH));
-    }
-    if model_name.starts_with("deepseek") {
-        return Some(format!("{}{}", crate::constants::DEEPSEEK_BASE_URL, crate::constants::DEFAULT_CHAT_COMPLETIONS_PATH));
-    }
-    if model_name.starts_with("qianfan") {
-        return Some(format!("{}{}", crate::constants::QIANFAN_BASE_URL, crate::constants::QIANFAN_CHAT_COMPLETIONS_PATH));
-    }
-    None
+    registry::provider_for_model(model_name).endpoint("")
 }
 
-/// Constructs a full URL by combining a base URL with the default chat completions path.
-/// Handles various cases where the base URL may already include parts of the path.
+/// Constructs a full URL by combining a base URL with the path of a provider endpoint.
+///
+/// The base URL may already include the whole path, as `https://api.openai.com/v1/chat/completions`,
+/// or its beginning, as `https://open.bigmodel.cn/api/paas/v4` or a proxy URL ending with `/v1`;
+/// only the missing segments are appended.
 ///
 /// # Arguments
 ///
 /// * `base_url` - The base URL provided by the user.
+/// * `path` - The path of the provider endpoint, as `/v1/chat/completions`.
 ///
 /// # Returns
 ///
 /// * `String` containing the full URL.
-pub fn construct_full_url(base_url: &str) -> String {
-    // Remove trailing slash from base URL if present
+pub fn construct_endpoint(base_url: &str, path: &str) -> String {
     let trimmed_base = base_url.trim_end_matches('/');
-    
-    // Check if the base URL already contains the full chat completions path
-    if trimmed_base.ends_with("/v1/chat/completions") {
-        return trimmed_base.to_string();
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let base_path = reqwest::Url::parse(trimmed_base)
+        .map(|url| url.path().trim_end_matches('/').to_string())
+        .unwrap_or_default();
+    let base_segments: Vec<&str> = base_path.split('/').filter(|s| !s.is_empty()).collect();
+
+    // The base URL already ends with the endpoint, at least with its last two segments
+    let min_suffix = segments.len().min(2);
+    for len in (min_suffix..=segments.len()).rev() {
+        if len > 0 && base_segments.ends_with(&segments[segments.len() - len..]) {
+            return trimmed_base.to_string();
+        }
     }
-    
-    // Check if the base URL ends with /v1, in which case we only need to add /chat/completions
-    if trimmed_base.ends_with("/v1") {
-        return format!("{}{}", trimmed_base, "/chat/completions");
+
+    // The base URL ends with the beginning of the endpoint path, append the rest
+    for len in (1..=segments.len().min(base_segments.len())).rev() {
+        if base_segments.ends_with(&segments[..len]) {
+            return format!("{}/{}", trimmed_base, segments[len..].join("/"));
+        }
     }
-    
-    // Add the default path if not already present
-    format!("{}{}", trimmed_base, crate::constants::DEFAULT_CHAT_COMPLETIONS_PATH)
+    format!("{}/{}", trimmed_base, segments.join("/"))
+}
+
+/// Returns whether the URL points to the local machine.
+///
+/// # Arguments
+///
+/// * `url` - The API url.
+///
+/// # Returns
+///
+/// * `bool` - `true` for `localhost` and loopback addresses.
+pub fn is_local_url(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .is_some_and(|host| {
+            host == "localhost" || host == "127.0.0.1" || host == "[::1]" || host == "0.0.0.0"
+        })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::cli::http::{chat, construct_full_url};
+    use crate::{
+        cli::{
+            ai_configer::AiConfig,
+            http::{backoff_delay, chat, construct_endpoint, is_local_url},
+        },
+        constants::DEFAULT_CHAT_COMPLETIONS_PATH,
+    };
 
     #[tokio::test]
     async fn test_chat_success() {
+        let ai = AiConfig {
+            url: format!("{}{}", crate::constants::QWEN_BASE_URL, crate::constants::QWEN_CHAT_COMPLETIONS_PATH),
+            model: "qwen2.5-0.5b-instruct".into(),
+            apikey: "sk-".into(),
+            language: "English".into(),
+            provider: None,
+            stream: false,
+            connect_timeout_secs: 10,
+            read_timeout_secs: 60,
+            max_retries: 0,
+            fallbacks: Vec::new(),
+        };
         let result = chat(
-            format!("{}{}", crate::constants::QWEN_BASE_URL, crate::constants::QWEN_CHAT_COMPLETIONS_PATH),
-            "qwen2.5-0.5b-instruct".into(),
-            "sk-".into(),
+            &ai,
             Some("You are a helpful assistant.".to_string()),
             "讲个笑话".into(),
             false,
         )
         .await;
-        if result.is_err() {
-            println!("模型报错: {}", result.unwrap_err());
-        } else {
-            println!("模型回复: {}", result.unwrap());
+        match result {
+            Ok(answer) => println!("模型回复: {}", answer),
+            Err(e) => println!("模型报错: {}", e),
         }
     }
 
@@ -234,32 +383,79 @@ mod tests {
     fn test_construct_full_url() {
         // Test with base URL without any path
         assert_eq!(
-            construct_full_url("https://api.openai.com"),
+            construct_endpoint("https://api.openai.com", DEFAULT_CHAT_COMPLETIONS_PATH),
             "https://api.openai.com/v1/chat/completions"
         );
         
         // Test with base URL ending with /v1
         assert_eq!(
-            construct_full_url("https://api.openai.com/v1"),
+            construct_endpoint("https://api.openai.com/v1", DEFAULT_CHAT_COMPLETIONS_PATH),
             "https://api.openai.com/v1/chat/completions"
         );
         
         // Test with base URL already containing the full path
         assert_eq!(
-            construct_full_url("https://api.openai.com/v1/chat/completions"),
+            construct_endpoint("https://api.openai.com/v1/chat/completions", DEFAULT_CHAT_COMPLETIONS_PATH),
             "https://api.openai.com/v1/chat/completions"
         );
         
         // Test with base URL ending with trailing slash
         assert_eq!(
-            construct_full_url("https://api.openai.com/"),
+            construct_endpoint("https://api.openai.com/", DEFAULT_CHAT_COMPLETIONS_PATH),
             "https://api.openai.com/v1/chat/completions"
         );
         
         // Test with base URL ending with /v1 and trailing slash
         assert_eq!(
-            construct_full_url("https://api.openai.com/v1/"),
+            construct_endpoint("https://api.openai.com/v1/", DEFAULT_CHAT_COMPLETIONS_PATH),
             "https://api.openai.com/v1/chat/completions"
         );
     }
+
+    #[test]
+    fn test_construct_endpoint() {
+        assert_eq!(
+            construct_endpoint("https://api.anthropic.com", "/v1/messages"),
+            "https://api.anthropic.com/v1/messages"
+        );
+        assert_eq!(
+            construct_endpoint("https://api.anthropic.com/v1/", "/v1/messages"),
+            "https://api.anthropic.com/v1/messages"
+        );
+        assert_eq!(
+            construct_endpoint("https://ark.cn-beijing.volces.com/api/v3", "/api/v3/chat/completions"),
+            "https://ark.cn-beijing.volces.com/api/v3/chat/completions"
+        );
+        // A complete OpenAI-style endpoint is kept even when the provider path differs
+        assert_eq!(
+            construct_endpoint(
+                "https://proxy.example.com/v1/chat/completions",
+                "/compatible-mode/v1/chat/completions"
+            ),
+            "https://proxy.example.com/v1/chat/completions"
+        );
+        assert_eq!(
+            construct_endpoint("http://localhost:11434", "/api/chat"),
+            "http://localhost:11434/api/chat"
+        );
+    }
+
+    #[test]
+    fn test_is_local_url() {
+        assert!(is_local_url("http://localhost:11434"));
+        assert!(is_local_url("http://127.0.0.1:8080/v1"));
+        assert!(is_local_url("http://[::1]:8080"));
+        assert!(!is_local_url("https://api.openai.com"));
+        assert!(!is_local_url(""));
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        for attempt in 1..5 {
+            let delay = backoff_delay(attempt).as_millis() as u64;
+            let base = 1000 << (attempt - 1);
+            assert!(delay >= base && delay <= base + base / 2);
+        }
+        assert!(backoff_delay(100).as_secs() <= 90);
+    }
 }
@@ -0,0 +1,118 @@
+use lazy_static::lazy_static;
+use std::{fs, path::Path};
+
+use crate::{
+    cli::{repo_config, verbose::print_verbose},
+    constants::CUSTOM_SECTION_NAME,
+};
+
+/// The file listing the paths whose content is never sent to the AI, found by
+/// [`repo_config::find_in_repo`].
+pub const IGNORE_FILE: &str = ".gimignore";
+/// The key of the `[user]` section listing the ignored paths.
+pub const IGNORE_KEY: &str = "ignore";
+
+lazy_static! {
+    // Shared by the several git commands collecting the diffs of a run
+    static ref EXCLUDE_PATHSPECS: Vec<String> = ignore_patterns()
+        .iter()
+        .filter_map(|pattern| to_pathspec(pattern))
+        .collect();
+}
+
+/// Returns the pathspecs excluding the ignored paths, to be appended after `--` to the git
+/// commands collecting the content of the changes.
+///
+/// The ignored files are still listed by `--name-status`, only their content is left out.
+pub fn exclude_pathspecs() -> &'static [String] {
+    &EXCLUDE_PATHSPECS
+}
+
+/// Returns the ignore patterns of the `.gimignore` file and of the `ignore` list of the
+/// `[user]` config section.
+fn ignore_patterns() -> Vec<String> {
+    let mut patterns = Vec::new();
+    if let Some(path) = repo_config::find_in_repo(Path::new(IGNORE_FILE)) {
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                print_verbose(&format!("Use the ignore file '{}'", path.display()));
+                patterns.extend(content.lines().map(str::to_string));
+            }
+            Err(e) => eprintln!("Warning: failed to read '{}': {}", path.display(), e),
+        }
+    }
+    if let Ok(value) = repo_config::get_config_value(CUSTOM_SECTION_NAME, IGNORE_KEY) {
+        match value.as_array() {
+            Some(list) => patterns.extend(
+                list.iter()
+                    .filter_map(|pattern| pattern.as_str())
+                    .map(str::to_string),
+            ),
+            None => eprintln!(
+                "Warning: '{}' of the '[{}]' config section should be a list of patterns",
+                IGNORE_KEY, CUSTOM_SECTION_NAME
+            ),
+        }
+    }
+    patterns
+}
+
+/// Converts a `.gitignore` style pattern into a git pathspec excluding it.
+///
+/// A pattern without a slash matches at any depth, a leading slash anchors it at the root of
+/// the repository and a trailing slash matches everything under a directory.
+///
+/// # Returns
+///
+/// * `Some(String)` containing the pathspec.
+/// * `None` for blank lines, comments and negated patterns, which are not supported.
+fn to_pathspec(pattern: &str) -> Option<String> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() || pattern.starts_with('#') {
+        return None;
+    }
+    if pattern.starts_with('!') {
+        eprintln!(
+            "Warning: negated ignore pattern '{}' is not supported, skipped",
+            pattern
+        );
+        return None;
+    }
+    let (pattern, directory) = match pattern.strip_suffix('/') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut glob = match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if pattern.contains('/') => pattern.to_string(),
+        None => format!("**/{}", pattern),
+    };
+    if directory {
+        glob.push_str("/**");
+    }
+    // Relative to the root of the repository, whatever the current directory
+    Some(format!(":(top,exclude,glob){}", glob))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_pathspec;
+
+    #[test]
+    fn test_to_pathspec() {
+        assert_eq!(
+            to_pathspec("*.snap"),
+            Some(":(top,exclude,glob)**/*.snap".to_string())
+        );
+        assert_eq!(
+            to_pathspec("/vendor/"),
+            Some(":(top,exclude,glob)vendor/**".to_string())
+        );
+        assert_eq!(
+            to_pathspec("web/dist"),
+            Some(":(top,exclude,glob)web/dist".to_string())
+        );
+        assert_eq!(to_pathspec("# comment"), None);
+        assert_eq!(to_pathspec("  "), None);
+    }
+}
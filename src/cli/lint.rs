@@ -0,0 +1,277 @@
+use std::{fs, path::Path, process::Command};
+
+use gim_config::config;
+use serde_json::Value as Json;
+use toml::Value;
+
+use crate::cli::{
+    conventional::{CASES, CaseRule, SubjectRules},
+    repo_config,
+    verbose::print_verbose,
+};
+
+/// The config section holding the commit message rules.
+pub const LINT_SECTION: &str = "lint";
+/// The key of the global `[lint]` section allowing node to read JavaScript commitlint configs.
+const ALLOW_NODE_KEY: &str = "allow_node";
+
+/// The commitlint config files read as JSON, found by [`repo_config::find_in_repo`].
+const COMMITLINT_JSON_FILES: [&str; 2] = [".commitlintrc.json", ".commitlintrc"];
+/// The commitlint config files evaluated by node, only when allowed by the global config, see
+/// [`node_allowed`].
+const COMMITLINT_JS_FILES: [&str; 6] = [
+    "commitlint.config.js",
+    "commitlint.config.cjs",
+    "commitlint.config.mjs",
+    ".commitlintrc.js",
+    ".commitlintrc.cjs",
+    ".commitlintrc.mjs",
+];
+/// Prints the configuration exported by a JavaScript config file as JSON.
+const NODE_EXPORT_SCRIPT: &str = "const { pathToFileURL } = await import('node:url'); \
+    const m = await import(pathToFileURL(process.argv[1]).href); \
+    console.log(JSON.stringify(m.default ?? m));";
+
+/// Returns the commit message rules of the repository: its commitlint config file, with the
+/// `[lint]` config section over it.
+///
+/// # Returns
+///
+/// * `Some(SubjectRules)` containing the rules.
+/// * `None` if there is neither a commitlint config file nor a `[lint]` section.
+pub fn load_rules() -> Option<SubjectRules> {
+    let commitlint = read_commitlint_config();
+    let lint = repo_config::get_config()
+        .ok()
+        .and_then(|config| config.get(LINT_SECTION).cloned());
+    if commitlint.is_none() && lint.is_none() {
+        return None;
+    }
+    let mut rules = commitlint
+        .map(|config| from_commitlint(&config))
+        .unwrap_or_default();
+    if let Some(lint) = lint {
+        apply_lint_table(&mut rules, &lint);
+    }
+    print_verbose(&format!("Commit message rules: {:?}", rules));
+    Some(rules)
+}
+
+fn read_commitlint_config() -> Option<Json> {
+    for name in COMMITLINT_JSON_FILES {
+        let Some(path) = repo_config::find_in_repo(Path::new(name)) else {
+            continue;
+        };
+        let parsed = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(config) => {
+                print_verbose(&format!("Use the commitlint config '{}'", path.display()));
+                return Some(config);
+            }
+            Err(e) => eprintln!("Warning: ignore invalid '{}': {}", path.display(), e),
+        }
+    }
+    for name in COMMITLINT_JS_FILES {
+        let Some(path) = repo_config::find_in_repo(Path::new(name)) else {
+            continue;
+        };
+        if !node_allowed() {
+            print_verbose(&format!(
+                "Ignore '{}', set 'allow_node = true' in the '[{}]' section of the global config file to read it with node",
+                path.display(),
+                LINT_SECTION
+            ));
+            return None;
+        }
+        let output = Command::new("node")
+            .args(["--input-type=module", "-e", NODE_EXPORT_SCRIPT])
+            .arg(&path)
+            .output();
+        print_verbose(&format!("Run node to read '{}'", path.display()));
+        match output {
+            Ok(output) if output.status.success() => {
+                match serde_json::from_slice(&output.stdout) {
+                    Ok(config) => return Some(config),
+                    Err(e) => eprintln!("Warning: ignore invalid '{}': {}", path.display(), e),
+                }
+            }
+            _ => eprintln!(
+                "Warning: failed to read '{}' with node, use a '.commitlintrc.json' file or the '[{}]' config section instead",
+                path.display(),
+                LINT_SECTION
+            ),
+        }
+        return None;
+    }
+    None
+}
+
+/// Checks whether the JavaScript commitlint config files may be run by node.
+///
+/// Running them executes code of the repository, so that it is allowed by `allow_node` of the
+/// `[lint]` section of the global config file only, never by a repository config file.
+fn node_allowed() -> bool {
+    config::get_config()
+        .ok()
+        .and_then(|config| config.get(LINT_SECTION)?.get(ALLOW_NODE_KEY)?.as_bool())
+        .unwrap_or(false)
+}
+
+/// Reads the rules of a commitlint configuration.
+///
+/// `@commitlint/config-conventional` is the only shared configuration known in `extends`,
+/// other ones are left out. Rules are `[level, "always" | "never", value]`, level `0`
+/// disabling them.
+fn from_commitlint(config: &Json) -> SubjectRules {
+    let extends: Vec<&str> = match config.get("extends") {
+        Some(Json::String(name)) => vec![name.as_str()],
+        Some(Json::Array(names)) => names.iter().filter_map(Json::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let mut rules = SubjectRules::default();
+    if extends.iter().any(|name| name.contains("config-conventional")) {
+        rules.max_length = 100;
+        rules.body_max_line_length = Some(100);
+        rules.subject_case = Some(CaseRule {
+            always: false,
+            cases: ["sentence-case", "start-case", "pascal-case", "upper-case"]
+                .iter()
+                .map(|case| case.to_string())
+                .collect(),
+        });
+    }
+    let Some(Json::Object(config_rules)) = config.get("rules") else {
+        return rules;
+    };
+    for (name, rule) in config_rules {
+        let Some(rule) = rule.as_array() else {
+            continue;
+        };
+        let level = rule.first().and_then(Json::as_u64).unwrap_or(0);
+        let always = rule.get(1).and_then(Json::as_str) != Some("never");
+        let value = rule.get(2);
+        let strings = || -> Vec<String> {
+            match value {
+                Some(Json::String(value)) => vec![value.clone()],
+                Some(Json::Array(values)) => values
+                    .iter()
+                    .filter_map(Json::as_str)
+                    .map(str::to_string)
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+        let number = || value.and_then(Json::as_u64).map(|n| n as usize);
+        match (name.as_str(), level) {
+            ("subject-case", 0) => rules.subject_case = None,
+            ("body-max-line-length", 0) => rules.body_max_line_length = None,
+            (_, 0) => {}
+            ("type-enum", _) if always => rules.types = strings(),
+            ("scope-enum", _) if always => rules.scopes = strings(),
+            ("scope-empty", _) => {
+                rules.scope_required = !always;
+                rules.scope_forbidden = always;
+            }
+            ("subject-case", _) => {
+                rules.subject_case = Some(CaseRule {
+                    always,
+                    cases: strings(),
+                })
+            }
+            ("header-max-length", _) => rules.max_length = number().unwrap_or(rules.max_length),
+            ("body-max-line-length", _) => rules.body_max_line_length = number(),
+            _ => print_verbose(&format!("Commitlint rule '{}' is not checked", name)),
+        }
+    }
+    rules
+}
+
+/// Applies the `[lint]` config section over the rules.
+fn apply_lint_table(rules: &mut SubjectRules, lint: &Value) {
+    let strings = |key: &str| -> Option<Vec<String>> {
+        let values = lint.get(key)?.as_array()?;
+        Some(values.iter().filter_map(Value::as_str).map(str::to_string).collect())
+    };
+    let number = |key: &str| lint.get(key)?.as_integer().filter(|n| *n > 0).map(|n| n as usize);
+    if let Some(types) = strings("types") {
+        rules.types = types;
+    }
+    if let Some(scopes) = strings("scopes") {
+        rules.scopes = scopes;
+    }
+    if let Some(required) = lint.get("scope_required").and_then(Value::as_bool) {
+        rules.scope_required = required;
+    }
+    if let Some(case) = lint.get("subject_case").and_then(Value::as_str) {
+        if !CASES.contains(&case) {
+            eprintln!(
+                "Warning: unknown subject case '{}' of the '[{}]' config section, expected one of: {}",
+                case,
+                LINT_SECTION,
+                CASES.join(", ")
+            );
+        }
+        rules.subject_case = Some(CaseRule {
+            always: true,
+            cases: vec![case.to_string()],
+        });
+    }
+    if let Some(max_length) = number("header_max_length") {
+        rules.max_length = max_length;
+    }
+    if let Some(max_length) = number("body_max_line_length") {
+        rules.body_max_line_length = Some(max_length);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_lint_table, from_commitlint};
+    use crate::cli::conventional::CaseRule;
+
+    #[test]
+    fn test_from_commitlint() {
+        let config = serde_json::json!({
+            "extends": ["@commitlint/config-conventional"],
+            "rules": {
+                "type-enum": [2, "always", ["feat", "fix"]],
+                "scope-enum": [2, "always", ["parser", "cli"]],
+                "scope-empty": [2, "never"],
+                "header-max-length": [2, "always", 60],
+                "body-max-line-length": [0, "always", 100]
+            }
+        });
+        let rules = from_commitlint(&config);
+        assert_eq!(rules.types, vec!["feat", "fix"]);
+        assert_eq!(rules.scopes, vec!["parser", "cli"]);
+        assert!(rules.scope_required);
+        assert_eq!(rules.max_length, 60);
+        assert_eq!(rules.body_max_line_length, None);
+        assert!(rules.subject_case.is_some_and(|case| !case.always));
+    }
+
+    #[test]
+    fn test_apply_lint_table() {
+        let mut rules = Default::default();
+        let lint: toml::Value = toml::from_str(
+            r#"
+            types = ["feat", "fix", "chore"]
+            subject_case = "lower-case"
+            header_max_length = 50
+            "#,
+        )
+        .unwrap();
+        apply_lint_table(&mut rules, &lint);
+        assert_eq!(rules.types, vec!["feat", "fix", "chore"]);
+        assert_eq!(rules.max_length, 50);
+        assert_eq!(
+            rules.subject_case,
+            Some(CaseRule {
+                always: true,
+                cases: vec!["lower-case".to_string()],
+            })
+        );
+    }
+}
@@ -1,9 +1,27 @@
 pub mod ai_configer;
+pub mod ai_profile;
+pub mod breaking;
+pub mod budget;
 pub mod command;
+pub mod conventional;
 pub mod custom_param;
+pub mod diff;
 pub mod entry;
+pub mod hook;
 pub mod http;
+pub mod ignore;
+pub mod lint;
+pub mod placeholder;
 pub mod prompt;
+pub mod provider;
+pub mod repo_config;
+pub mod review;
+pub mod scope;
+pub mod secret;
+pub mod summarize;
+pub mod template;
+pub mod ticket;
+pub mod trailer;
 pub mod update;
 pub mod verbose;
 
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use crate::cli::{
+    budget::{self, MINIFIED_SUFFIXES},
+    diff::git_output,
+    verbose::print_verbose,
+};
+
+/// A line longer than this is taken for minified code.
+const MINIFIED_LINE_LENGTH: usize = 1000;
+
+/// Why the content of a file is not sent to the AI.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Unreadable {
+    Binary,
+    Minified,
+    Generated,
+}
+
+impl Unreadable {
+    fn name(&self) -> &'static str {
+        match self {
+            Unreadable::Binary => "binary",
+            Unreadable::Minified => "minified",
+            Unreadable::Generated => "generated",
+        }
+    }
+}
+
+/// Replaces the diffs of binary files, minified files and files marked `linguist-generated`
+/// or `-diff` in `.gitattributes` with a one-line placeholder, such as
+/// `binary file changed, 12 KB -> 14 KB`.
+///
+/// # Arguments
+///
+/// * `full_diff` - The output of `git diff` or `git show`.
+/// * `old_rev` - The revision before the changes, such as `HEAD`.
+/// * `new_rev` - The revision of the changes, empty for the index.
+///
+/// # Returns
+///
+/// * `String` containing the diff with the placeholders.
+pub fn replace_unreadable(full_diff: &str, old_rev: &str, new_rev: &str) -> String {
+    let files = budget::split_files(full_diff);
+    if files.is_empty() {
+        return full_diff.to_string();
+    }
+    let paths: Vec<&str> = files.iter().map(|file| file.path).collect();
+    let attributes = read_attributes(&paths);
+
+    // Keep whatever comes before the first file diff
+    let first = full_diff.find("diff --git ").unwrap_or(0);
+    let mut out = full_diff[..first].to_string();
+    for file in files {
+        let kind = if is_binary(file.text) {
+            Some(Unreadable::Binary)
+        } else if let Some(kind) = attributes.get(file.path) {
+            Some(*kind)
+        } else if is_minified(file.path, file.text) {
+            Some(Unreadable::Minified)
+        } else {
+            None
+        };
+        let Some(kind) = kind else {
+            out.push_str(file.text);
+            continue;
+        };
+        print_verbose(&format!("Replace the {} file '{}' with a placeholder", kind.name(), file.path));
+        let header = file.text.lines().next().unwrap_or_default();
+        let old_size = object_size(old_rev, file.path);
+        let new_size = object_size(new_rev, file.path);
+        out.push_str(&format!("{}\n{}\n", header, placeholder(kind, old_size, new_size)));
+    }
+    out
+}
+
+fn is_binary(text: &str) -> bool {
+    text.lines()
+        .any(|line| line.starts_with("Binary files ") || line == "GIT binary patch")
+}
+
+fn is_minified(path: &str, text: &str) -> bool {
+    MINIFIED_SUFFIXES.iter().any(|suffix| path.ends_with(suffix))
+        || text.lines().any(|line| {
+            line.starts_with('+') && line.chars().count() > MINIFIED_LINE_LENGTH
+        })
+}
+
+/// Reads the `diff` and `linguist-generated` attributes of the files from the index.
+///
+/// # Returns
+///
+/// * `HashMap<String, Unreadable>` containing the files marked `-diff` as binary, and the files
+///   marked `linguist-generated` as generated.
+fn read_attributes(paths: &[&str]) -> HashMap<String, Unreadable> {
+    let mut args = vec!["check-attr", "--cached", "diff", "linguist-generated", "--"];
+    args.extend(paths);
+    let mut kinds = HashMap::new();
+    // <path>: <attribute>: <value>
+    for line in git_output(&args).lines() {
+        let mut fields = line.rsplitn(3, ": ");
+        let (Some(value), Some(attribute), Some(path)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let kind = match (attribute, value) {
+            ("diff", "unset") => Unreadable::Binary,
+            ("linguist-generated", "set" | "true") => Unreadable::Generated,
+            _ => continue,
+        };
+        // A file both `-diff` and generated is shown as binary
+        kinds.entry(path.to_string()).or_insert(kind);
+    }
+    kinds
+}
+
+/// Returns the size in bytes of a file at a revision, `None` if it doesn't exist there.
+fn object_size(rev: &str, path: &str) -> Option<u64> {
+    git_output(&["cat-file", "-s", &format!("{}:{}", rev, path)])
+        .parse()
+        .ok()
+}
+
+fn placeholder(kind: Unreadable, old_size: Option<u64>, new_size: Option<u64>) -> String {
+    match (old_size, new_size) {
+        (None, Some(new_size)) => format!("{} file added, {}", kind.name(), format_size(new_size)),
+        (Some(old_size), Some(new_size)) => format!(
+            "{} file changed, {} -> {}",
+            kind.name(),
+            format_size(old_size),
+            format_size(new_size)
+        ),
+        _ => format!("{} file changed", kind.name()),
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+    if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{} KB", (bytes + KB / 2) / KB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Unreadable, format_size, is_binary, is_minified, placeholder};
+
+    #[test]
+    fn test_detect_unreadable() {
+        assert!(is_binary(
+            "diff --git a/logo.png b/logo.png\nindex 1..2 100644\nBinary files a/logo.png and b/logo.png differ\n"
+        ));
+        assert!(!is_binary("diff --git a/a.rs b/a.rs\n+Binary files are fine\n"));
+        assert!(is_minified("static/app.min.js", ""));
+        assert!(is_minified("static/app.js", &format!("+{}\n", "x;".repeat(600))));
+        assert!(!is_minified("src/main.rs", "+fn main() {}\n"));
+    }
+
+    #[test]
+    fn test_placeholder() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(12 * 1024 + 100), "12 KB");
+        assert_eq!(
+            placeholder(Unreadable::Binary, Some(12 * 1024), Some(14 * 1024)),
+            "binary file changed, 12 KB -> 14 KB"
+        );
+        assert_eq!(
+            placeholder(Unreadable::Generated, None, Some(2 * 1024 * 1024)),
+            "generated file added, 2.0 MB"
+        );
+    }
+}
@@ -1,13 +1,61 @@
 use gim_config::directory;
 use indoc::indoc;
-use std::{fs, io::Result, path::PathBuf};
+use std::{
+    fs,
+    io::Result,
+    path::{Path, PathBuf},
+};
 
-use crate::constants::{DIFF_PROMPT_FILE, SUBJECT_PROMPT_FILE};
+use crate::{
+    cli::{repo_config, verbose::print_verbose},
+    constants::{DIFF_PROMPT_FILE, REPO_PROMPT_DIR, SUBJECT_PROMPT_FILE},
+};
+
+/// The config section holding the `diff` and `subject` prompts, overriding the prompt files.
+pub const PROMPT_SECTION: &str = "prompt";
 
 fn file_dirs() -> Result<PathBuf> {
     directory::config_dir()
 }
 
+/// Returns the prompt file of the repository, such as `.gim/diff_prompt.txt`, found by
+/// [`repo_config::find_in_repo`].
+///
+/// # Arguments
+///
+/// * `file_name` - The name of the prompt file.
+///
+/// # Returns
+///
+/// * `Some(PathBuf)` of the nearest prompt file.
+/// * `None` if the repository has no such prompt file.
+pub fn repo_prompt_path(file_name: &str) -> Option<PathBuf> {
+    repo_config::find_in_repo(&Path::new(REPO_PROMPT_DIR).join(file_name))
+}
+
+fn repo_prompt(file_name: &str) -> Option<String> {
+    let path = repo_prompt_path(file_name)?;
+    match fs::read_to_string(&path) {
+        Ok(prompt) if !prompt.trim().is_empty() => {
+            print_verbose(&format!("Use the repository prompt file '{}'", path.display()));
+            Some(prompt)
+        }
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("Failed to read prompt from file '{}': {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Returns the prompt set by the `[prompt]` section of the configuration, if any.
+fn config_prompt(key: &str) -> Option<String> {
+    let prompt = repo_config::get_config_value(PROMPT_SECTION, key).ok()?;
+    let prompt = prompt.as_str().filter(|prompt| !prompt.trim().is_empty())?;
+    print_verbose(&format!("Use the {} prompt of the '[{}]' config section", key, PROMPT_SECTION));
+    Some(prompt.to_string())
+}
+
 fn trim_diff_prompt() -> String {
     indoc!(r#"
         You are an expert developer specialist in creating git commits.
@@ -55,10 +103,19 @@ fn trim_subject_prompt() -> String {
 }
 /// Returns the diff prompt string, reading from file if available, or using the default if not.
 ///
+/// The prompt file of the repository takes precedence over the `[prompt]` config section,
+/// which takes precedence over the global prompt file.
+///
 /// # Returns
 ///
 /// * `String` containing the diff prompt.
 pub fn get_diff_prompt() -> String {
+    if let Some(prompt) = repo_prompt(DIFF_PROMPT_FILE) {
+        return prompt;
+    }
+    if let Some(prompt) = config_prompt("diff") {
+        return prompt;
+    }
     let trimmed = trim_diff_prompt();
     let path = match file_dirs() {
         Ok(p) => p.join(DIFF_PROMPT_FILE),
@@ -83,10 +140,19 @@ pub fn get_diff_prompt() -> String {
 
 /// Returns the subject prompt string, reading from file if available, or using the default if not.
 ///
+/// The prompt file of the repository takes precedence over the `[prompt]` config section,
+/// which takes precedence over the global prompt file.
+///
 /// # Returns
 ///
 /// * `String` containing the subject prompt.
 pub fn get_subject_prompt() -> String {
+    if let Some(prompt) = repo_prompt(SUBJECT_PROMPT_FILE) {
+        return prompt;
+    }
+    if let Some(prompt) = config_prompt("subject") {
+        return prompt;
+    }
     let trimmed = trim_subject_prompt();
     let path = match file_dirs() {
         Ok(p) => p.join(SUBJECT_PROMPT_FILE),
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+use super::StreamEvent;
+
+/// The version of the Messages API sent in the `anthropic-version` header.
+pub const API_VERSION: &str = "2023-06-01";
+
+const MAX_TOKENS: u32 = 1024;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Request {
+    model: String,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<Message>,
+    temperature: f32,
+    stream: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Response {
+    content: Option<Vec<ContentBlock>>,
+    error: Option<ResponseError>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ContentBlock {
+    r#type: String,
+    text: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ResponseError {
+    r#type: Option<String>,
+    message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct StreamChunk {
+    r#type: String,
+    delta: Option<StreamDelta>,
+    error: Option<ResponseError>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct StreamDelta {
+    text: Option<String>,
+}
+
+/// Builds an Anthropic Messages API request body, with the system prompt at the top level.
+pub fn request_body(
+    model: &str,
+    system: Option<String>,
+    user: String,
+    stream: bool,
+) -> Result<serde_json::Value, Box<dyn Error>> {
+    let request_body = Request {
+        model: model.to_string(),
+        max_tokens: MAX_TOKENS,
+        system,
+        messages: vec![Message {
+            role: "user".to_string(),
+            content: user,
+        }],
+        temperature: 0.3,
+        stream,
+    };
+    Ok(serde_json::to_value(request_body)?)
+}
+
+/// Extracts the answer from the text blocks of an Anthropic Messages API response body.
+pub fn parse_response(res_text: &str) -> Result<String, Box<dyn Error>> {
+    let res: Response = serde_json::from_str(res_text)?;
+    if let Some(error) = res.error {
+        return Err(error.message.into());
+    }
+    if let Some(content) = res.content {
+        return Ok(content
+            .into_iter()
+            .filter(|block| block.r#type == "text")
+            .filter_map(|block| block.text)
+            .collect());
+    }
+    Err("unkown exception".into())
+}
+
+/// Parses a line of an Anthropic Messages API event stream.
+///
+/// # Returns
+///
+/// * `Ok(Some(StreamEvent))` for text deltas and for the `message_stop` event.
+/// * `Ok(None)` for event names, empty lines and other events.
+/// * `Err(Box<dyn Error>)` if the event is invalid or reports an error.
+pub fn parse_stream_line(line: &str) -> Result<Option<StreamEvent>, Box<dyn Error>> {
+    let Some(data) = line.trim().strip_prefix("data:") else {
+        return Ok(None);
+    };
+    let chunk: StreamChunk = serde_json::from_str(data.trim())?;
+    match chunk.r#type.as_str() {
+        "content_block_delta" => Ok(chunk
+            .delta
+            .and_then(|delta| delta.text)
+            .filter(|text| !text.is_empty())
+            .map(StreamEvent::Content)),
+        "message_stop" => Ok(Some(StreamEvent::Done)),
+        "error" => Err(chunk
+            .error
+            .map(|error| error.message)
+            .unwrap_or_else(|| "unkown exception".to_string())
+            .into()),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StreamEvent, parse_response, parse_stream_line, request_body};
+
+    #[test]
+    fn test_request_body() {
+        let body = request_body("claude-sonnet-4-5", Some("system".into()), "user".into(), true)
+            .unwrap();
+        assert_eq!(body["system"], "system");
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["stream"], true);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let answer = parse_response(
+            r#"{"type":"message","content":[{"type":"text","text":"feat: add login"}],"stop_reason":"end_turn"}"#,
+        );
+        assert_eq!(answer.unwrap(), "feat: add login");
+
+        let error = parse_response(
+            r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#,
+        );
+        assert_eq!(error.unwrap_err().to_string(), "invalid x-api-key");
+    }
+
+    #[test]
+    fn test_parse_stream_line() {
+        assert_eq!(parse_stream_line("event: content_block_delta").unwrap(), None);
+        assert_eq!(
+            parse_stream_line(
+                r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Add"}}"#
+            )
+            .unwrap(),
+            Some(StreamEvent::Content("Add".to_string()))
+        );
+        assert_eq!(
+            parse_stream_line(r#"data: {"type":"ping"}"#).unwrap(),
+            None
+        );
+        assert_eq!(
+            parse_stream_line(r#"data: {"type":"message_stop"}"#).unwrap(),
+            Some(StreamEvent::Done)
+        );
+        assert!(
+            parse_stream_line(
+                r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use serde::Deserialize;
+use std::error::Error;
+
+use crate::cli::http::{construct_endpoint, is_local_url};
+
+pub mod anthropic;
+pub mod ollama;
+pub mod openai;
+pub mod registry;
+
+/// An event parsed from a line of a streaming response.
+#[derive(Debug, PartialEq)]
+pub enum StreamEvent {
+    Content(String),
+    Done,
+}
+
+/// The API format spoken by an AI provider.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiFormat {
+    /// The OpenAI chat-completions API, also spoken by most providers.
+    OpenAi,
+    /// The Anthropic Messages API.
+    Anthropic,
+    /// The native Ollama chat API.
+    Ollama,
+}
+
+impl ApiFormat {
+    /// Builds the request body in this format.
+    pub fn request_body(
+        &self,
+        model: &str,
+        system: Option<String>,
+        user: String,
+        stream: bool,
+    ) -> Result<serde_json::Value, Box<dyn Error>> {
+        match self {
+            ApiFormat::OpenAi => openai::request_body(model, system, user, stream),
+            ApiFormat::Anthropic => anthropic::request_body(model, system, user, stream),
+            ApiFormat::Ollama => ollama::request_body(model, system, user, stream),
+        }
+    }
+
+    /// Extracts the answer from a complete response body in this format.
+    pub fn parse_response(&self, res_text: &str) -> Result<String, Box<dyn Error>> {
+        match self {
+            ApiFormat::OpenAi => openai::parse_response(res_text),
+            ApiFormat::Anthropic => anthropic::parse_response(res_text),
+            ApiFormat::Ollama => ollama::parse_response(res_text),
+        }
+    }
+
+    /// Parses a line of a streaming response in this format.
+    pub fn parse_stream_line(&self, line: &str) -> Result<Option<StreamEvent>, Box<dyn Error>> {
+        match self {
+            ApiFormat::OpenAi => openai::parse_stream_line(line),
+            ApiFormat::Anthropic => anthropic::parse_stream_line(line),
+            ApiFormat::Ollama => ollama::parse_stream_line(line),
+        }
+    }
+
+    /// Returns the usual authentication of this format.
+    pub fn auth_style(&self) -> AuthStyle {
+        match self {
+            ApiFormat::OpenAi | ApiFormat::Ollama => AuthStyle::Bearer,
+            ApiFormat::Anthropic => AuthStyle::Header("x-api-key".to_string()),
+        }
+    }
+
+    /// Adds the headers required by this format, other than the authentication.
+    pub fn add_headers(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self {
+            ApiFormat::Anthropic => request.header("anthropic-version", anthropic::API_VERSION),
+            ApiFormat::OpenAi | ApiFormat::Ollama => request,
+        }
+    }
+}
+
+/// How the api key is sent to a provider.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuthStyle {
+    /// `Authorization: Bearer <apikey>`
+    Bearer,
+    /// The api key as the value of the given header.
+    Header(String),
+    /// The api key is never sent.
+    None,
+}
+
+/// An AI provider: where to send the requests and how to talk to it.
+///
+/// Only the endpoint and the API format are required, the request and the response
+/// handling defaults to the API format and can be customized for provider quirks.
+pub trait Provider: Send + Sync {
+    /// The name used by the `provider` key of the ai section.
+    fn name(&self) -> &str;
+
+    /// Whether the provider serves the given model when no provider is configured.
+    fn matches(&self, model: &str) -> bool;
+
+    /// The default base URL, empty if the user must configure one.
+    fn base_url(&self) -> &str;
+
+    /// The path of the chat endpoint, appended to the base URL.
+    fn path(&self) -> &str;
+
+    /// The API format spoken by the provider.
+    fn format(&self) -> ApiFormat;
+
+    /// How the api key is sent.
+    fn auth_style(&self) -> AuthStyle {
+        self.format().auth_style()
+    }
+
+    /// Whether the provider runs on the local machine and needs no api key.
+    fn is_local(&self) -> bool {
+        is_local_url(self.base_url())
+    }
+
+    /// The model name sent to the provider.
+    fn model_name<'a>(&self, model: &'a str) -> &'a str {
+        model
+    }
+
+    /// Adjusts the request body for the provider, after it was built by the API format.
+    fn customize_body(&self, _body: &mut serde_json::Value) {}
+
+    /// Returns the full endpoint URL, from the URL configured by the user or the default one.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(String)` containing the full URL.
+    /// * `None` if no URL is configured and the provider has no default.
+    fn endpoint(&self, url: &str) -> Option<String> {
+        if url.starts_with("http") {
+            return Some(construct_endpoint(url, self.path()));
+        }
+        if self.base_url().is_empty() {
+            return None;
+        }
+        Some(format!("{}{}", self.base_url(), self.path()))
+    }
+
+    /// Builds the request body.
+    fn request_body(
+        &self,
+        model: &str,
+        system: Option<String>,
+        user: String,
+        stream: bool,
+    ) -> Result<serde_json::Value, Box<dyn Error>> {
+        let mut body = self
+            .format()
+            .request_body(self.model_name(model), system, user, stream)?;
+        self.customize_body(&mut body);
+        Ok(body)
+    }
+
+    /// Adds the authentication and format headers to the request.
+    ///
+    /// No authentication header is added without an api key, as local providers don't need one.
+    fn authorize(
+        &self,
+        request: reqwest::RequestBuilder,
+        api_key: &str,
+    ) -> reqwest::RequestBuilder {
+        let request = self.format().add_headers(request);
+        if api_key.is_empty() {
+            return request;
+        }
+        match self.auth_style() {
+            AuthStyle::Bearer => request.header("Authorization", format!("Bearer {}", api_key)),
+            AuthStyle::Header(name) => request.header(name, api_key),
+            AuthStyle::None => request,
+        }
+    }
+
+    /// Extracts the answer from a complete response body.
+    fn parse_response(&self, res_text: &str) -> Result<String, Box<dyn Error>> {
+        self.format().parse_response(res_text)
+    }
+
+    /// Parses a line of a streaming response.
+    fn parse_stream_line(&self, line: &str) -> Result<Option<StreamEvent>, Box<dyn Error>> {
+        self.format().parse_stream_line(line)
+    }
+}
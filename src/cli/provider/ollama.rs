@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+use super::StreamEvent;
+
+/// The prefix of model names served by a local Ollama instance, e.g. `ollama/llama3.2`.
+pub const MODEL_PREFIX: &str = "ollama/";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Request {
+    model: String,
+    messages: Vec<Message>,
+    stream: bool,
+    options: RequestOptions,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RequestOptions {
+    temperature: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Response {
+    message: Option<Message>,
+    done: Option<bool>,
+    error: Option<String>,
+}
+
+/// Builds an Ollama `/api/chat` request body.
+pub fn request_body(
+    model: &str,
+    system: Option<String>,
+    user: String,
+    stream: bool,
+) -> Result<serde_json::Value, Box<dyn Error>> {
+    let mut messages = Vec::new();
+    if let Some(system) = system {
+        messages.push(Message {
+            role: "system".to_string(),
+            content: system,
+        });
+    }
+    messages.push(Message {
+        role: "user".to_string(),
+        content: user,
+    });
+    let request_body = Request {
+        model: model.to_string(),
+        messages,
+        stream,
+        options: RequestOptions { temperature: 0.3 },
+    };
+    Ok(serde_json::to_value(request_body)?)
+}
+
+/// Extracts the answer from an Ollama `/api/chat` response body.
+pub fn parse_response(res_text: &str) -> Result<String, Box<dyn Error>> {
+    let res: Response = serde_json::from_str(res_text)?;
+    if let Some(error) = res.error {
+        return Err(error.into());
+    }
+    if let Some(message) = res.message {
+        return Ok(message.content);
+    }
+    Err("unkown exception".into())
+}
+
+/// Parses a line of an Ollama `/api/chat` stream, which is a JSON object per line.
+///
+/// # Returns
+///
+/// * `Ok(Some(StreamEvent))` for content and for the final object marked as done.
+/// * `Ok(None)` for empty lines and objects without content.
+/// * `Err(Box<dyn Error>)` if the line is invalid or reports an error.
+pub fn parse_stream_line(line: &str) -> Result<Option<StreamEvent>, Box<dyn Error>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let res: Response = serde_json::from_str(line)?;
+    if let Some(error) = res.error {
+        return Err(error.into());
+    }
+    if res.done.unwrap_or(false) {
+        return Ok(Some(StreamEvent::Done));
+    }
+    Ok(res
+        .message
+        .map(|message| message.content)
+        .filter(|content| !content.is_empty())
+        .map(StreamEvent::Content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StreamEvent, parse_response, parse_stream_line, request_body};
+
+    #[test]
+    fn test_request_body() {
+        let body = request_body("llama3.2", Some("system".into()), "user".into(), false).unwrap();
+        assert_eq!(body["model"], "llama3.2");
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["role"], "user");
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let answer = parse_response(
+            r#"{"model":"llama3.2","message":{"role":"assistant","content":"feat: add login"},"done":true}"#,
+        );
+        assert_eq!(answer.unwrap(), "feat: add login");
+        assert!(parse_response(r#"{"error":"model 'llama9' not found"}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_stream_line() {
+        assert_eq!(
+            parse_stream_line(r#"{"message":{"role":"assistant","content":"Add"},"done":false}"#)
+                .unwrap(),
+            Some(StreamEvent::Content("Add".to_string()))
+        );
+        assert_eq!(
+            parse_stream_line(r#"{"message":{"role":"assistant","content":""},"done":true}"#)
+                .unwrap(),
+            Some(StreamEvent::Done)
+        );
+        assert_eq!(parse_stream_line("").unwrap(), None);
+    }
+}
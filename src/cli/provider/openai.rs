@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+use super::StreamEvent;
+use crate::cli::verbose::print_verbose;
+
+#[derive(Serialize, Deserialize, Debug, validator::Validate)]
+struct Message {
+    #[validate(length(min = 1))]
+    role: String,
+    #[validate(length(min = 1))]
+    content: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Request {
+    model: String,
+    messages: Vec<Message>,
+    temperature: f32,
+    stream: bool,
+    extra_body: RequestExtraBody,
+}
+
+impl Default for Request {
+    fn default() -> Self {
+        let empty_string = String::new();
+        Self {
+            model: empty_string,
+            messages: Default::default(),
+            temperature: 0.3,
+            stream: false,
+            extra_body: RequestExtraBody {
+                enable_thinking: false,
+            },
+        }
+    }
+}
+
+/// Fields read by some OpenAI-compatible services, such as Qwen models served by vLLM.
+#[derive(Serialize, Deserialize, Debug)]
+struct RequestExtraBody {
+    enable_thinking: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Response {
+    choices: Option<Vec<Choice>>,
+    error: Option<ResponseError>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Choice {
+    message: Message,
+    finish_reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ResponseError {
+    message: String,
+    r#type: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct StreamChunk {
+    choices: Option<Vec<StreamChoice>>,
+    error: Option<ResponseError>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct StreamChoice {
+    delta: StreamDelta,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct StreamDelta {
+    content: Option<String>,
+}
+
+/// Builds an OpenAI chat-completions request body.
+pub fn request_body(
+    model: &str,
+    system: Option<String>,
+    user: String,
+    stream: bool,
+) -> Result<serde_json::Value, Box<dyn Error>> {
+    let mut request_body = Request {
+        model: model.to_string(),
+        messages: vec![Message {
+            role: "user".to_string(),
+            content: user,
+        }],
+        stream,
+        ..Default::default()
+    };
+    if let Some(system) = system {
+        request_body.messages.push(Message {
+            role: "system".to_string(),
+            content: system,
+        });
+    }
+    Ok(serde_json::to_value(request_body)?)
+}
+
+/// Extracts the answer from an OpenAI chat-completions response body.
+pub fn parse_response(res_text: &str) -> Result<String, Box<dyn Error>> {
+    let res: Response = serde_json::from_str(res_text)?;
+
+    if let Some(res) = res.choices {
+        return Ok(res[0].message.content.clone());
+    }
+    print_verbose(&format!("{:?}", res));
+    if let Some(res) = res.error {
+        return Err(res.message.into());
+    }
+    Err("unkown exception".into())
+}
+
+/// Parses a line of an OpenAI chat-completions event stream.
+///
+/// # Returns
+///
+/// * `Ok(Some(StreamEvent))` for content and for the `[DONE]` terminator.
+/// * `Ok(None)` for empty lines, comments, other fields and chunks without content.
+/// * `Err(Box<dyn Error>)` if the chunk is invalid or reports an error.
+pub fn parse_stream_line(line: &str) -> Result<Option<StreamEvent>, Box<dyn Error>> {
+    let Some(data) = line.trim().strip_prefix("data:") else {
+        return Ok(None);
+    };
+    let data = data.trim();
+    if data == "[DONE]" {
+        return Ok(Some(StreamEvent::Done));
+    }
+    let chunk: StreamChunk = serde_json::from_str(data)?;
+    if let Some(error) = chunk.error {
+        return Err(error.message.into());
+    }
+    let content: String = chunk
+        .choices
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|choice| choice.delta.content)
+        .collect();
+    if content.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(StreamEvent::Content(content)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StreamEvent, parse_response, parse_stream_line};
+
+    #[test]
+    fn test_parse_response() {
+        let answer = parse_response(
+            r#"{"choices":[{"message":{"role":"assistant","content":"feat: add login"},"finish_reason":"stop"}]}"#,
+        );
+        assert_eq!(answer.unwrap(), "feat: add login");
+
+        let error = parse_response(r#"{"error":{"message":"invalid api key"}}"#);
+        assert_eq!(error.unwrap_err().to_string(), "invalid api key");
+    }
+
+    #[test]
+    fn test_parse_stream_line() {
+        assert_eq!(
+            parse_stream_line(r#"data: {"choices":[{"delta":{"content":"Add"}}]}"#).unwrap(),
+            Some(StreamEvent::Content("Add".to_string()))
+        );
+        assert_eq!(
+            parse_stream_line("data: [DONE]").unwrap(),
+            Some(StreamEvent::Done)
+        );
+
+        // Role-only chunks, keep-alive comments and empty lines carry no content
+        assert_eq!(
+            parse_stream_line(r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#).unwrap(),
+            None
+        );
+        assert_eq!(parse_stream_line(": keep-alive").unwrap(), None);
+        assert_eq!(parse_stream_line("").unwrap(), None);
+
+        assert!(parse_stream_line(r#"data: {"error":{"message":"quota exceeded"}}"#).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+use serde::Deserialize;
+use std::error::Error;
+
+use gim_config::config;
+
+use super::{ApiFormat, AuthStyle, Provider, ollama};
+use crate::{
+    cli::{ai_configer::AiConfig, http::is_local_url, verbose::print_verbose},
+    constants::*,
+};
+
+/// The config section declaring user-defined providers, one table per provider.
+pub const PROVIDERS_SECTION: &str = "providers";
+
+/// A provider shipped with gim.
+#[derive(Clone, Copy)]
+pub struct BuiltinProvider {
+    name: &'static str,
+    model_prefixes: &'static [&'static str],
+    base_url: &'static str,
+    path: &'static str,
+    format: ApiFormat,
+    /// Remove the matched model prefix from the model name sent to the provider.
+    strip_model_prefix: bool,
+    body_quirk: fn(&mut serde_json::Value),
+}
+
+fn no_quirk(_body: &mut serde_json::Value) {}
+
+/// Qwen3 models think before answering unless disabled, which is slow and not useful here.
+fn disable_thinking(body: &mut serde_json::Value) {
+    body["enable_thinking"] = serde_json::Value::Bool(false);
+}
+
+const fn builtin(
+    name: &'static str,
+    model_prefixes: &'static [&'static str],
+    base_url: &'static str,
+    path: &'static str,
+) -> BuiltinProvider {
+    BuiltinProvider {
+        name,
+        model_prefixes,
+        base_url,
+        path,
+        format: ApiFormat::OpenAi,
+        strip_model_prefix: false,
+        body_quirk: no_quirk,
+    }
+}
+
+/// The providers shipped with gim, matched in order by model name prefix.
+pub const BUILTIN_PROVIDERS: [BuiltinProvider; 10] = [
+    builtin("moonshot", &["moonshot"], MOONSHOT_BASE_URL, DEFAULT_CHAT_COMPLETIONS_PATH),
+    BuiltinProvider {
+        body_quirk: disable_thinking,
+        ..builtin("qwen", &["qwen"], QWEN_BASE_URL, QWEN_CHAT_COMPLETIONS_PATH)
+    },
+    builtin("openai", &["gpt"], GPT_BASE_URL, DEFAULT_CHAT_COMPLETIONS_PATH),
+    builtin("gemini", &["gemini"], GEMINI_BASE_URL, GEMINI_CHAT_COMPLETIONS_PATH),
+    builtin("doubao", &["doubao"], DOUBAO_BASE_URL, DOUBAO_CHAT_COMPLETIONS_PATH),
+    builtin("glm", &["glm"], GLM_BASE_URL, GLM_CHAT_COMPLETIONS_PATH),
+    builtin("deepseek", &["deepseek"], DEEPSEEK_BASE_URL, DEFAULT_CHAT_COMPLETIONS_PATH),
+    builtin("qianfan", &["qianfan"], QIANFAN_BASE_URL, QIANFAN_CHAT_COMPLETIONS_PATH),
+    BuiltinProvider {
+        format: ApiFormat::Anthropic,
+        ..builtin("anthropic", &["claude"], ANTHROPIC_BASE_URL, ANTHROPIC_MESSAGES_PATH)
+    },
+    BuiltinProvider {
+        format: ApiFormat::Ollama,
+        strip_model_prefix: true,
+        ..builtin("ollama", &[ollama::MODEL_PREFIX], OLLAMA_BASE_URL, OLLAMA_CHAT_PATH)
+    },
+];
+
+/// Any OpenAI-compatible service, used when no provider matches: the URL must be configured.
+const GENERIC_PROVIDER: BuiltinProvider = builtin("generic", &[], "", DEFAULT_CHAT_COMPLETIONS_PATH);
+
+impl Provider for BuiltinProvider {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn matches(&self, model: &str) -> bool {
+        self.model_prefixes
+            .iter()
+            .any(|prefix| model.starts_with(prefix))
+    }
+
+    fn base_url(&self) -> &str {
+        self.base_url
+    }
+
+    fn path(&self) -> &str {
+        self.path
+    }
+
+    fn format(&self) -> ApiFormat {
+        self.format
+    }
+
+    fn model_name<'a>(&self, model: &'a str) -> &'a str {
+        if !self.strip_model_prefix {
+            return model;
+        }
+        self.model_prefixes
+            .iter()
+            .find_map(|prefix| model.strip_prefix(prefix))
+            .unwrap_or(model)
+    }
+
+    fn customize_body(&self, body: &mut serde_json::Value) {
+        (self.body_quirk)(body)
+    }
+}
+
+/// A provider declared by the user in a `[providers.<name>]` table of the config file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CustomProvider {
+    #[serde(skip)]
+    name: String,
+    #[serde(default)]
+    base_url: String,
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default = "default_format")]
+    format: ApiFormat,
+    /// `bearer`, `header` or `none`; defaults to the usual authentication of the format.
+    auth: Option<String>,
+    /// The header carrying the api key when `auth` is `header`.
+    auth_header: Option<String>,
+    #[serde(default)]
+    model_prefixes: Vec<String>,
+    local: Option<bool>,
+    /// Extra fields merged into the request body.
+    body: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+fn default_path() -> String {
+    DEFAULT_CHAT_COMPLETIONS_PATH.to_string()
+}
+
+fn default_format() -> ApiFormat {
+    ApiFormat::OpenAi
+}
+
+impl Provider for CustomProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn matches(&self, model: &str) -> bool {
+        self.model_prefixes
+            .iter()
+            .any(|prefix| model.starts_with(prefix.as_str()))
+    }
+
+    fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn format(&self) -> ApiFormat {
+        self.format
+    }
+
+    fn auth_style(&self) -> AuthStyle {
+        match self.auth.as_deref() {
+            Some("none") => AuthStyle::None,
+            Some("bearer") => AuthStyle::Bearer,
+            Some("header") => AuthStyle::Header(
+                self.auth_header
+                    .clone()
+                    .unwrap_or_else(|| "api-key".to_string()),
+            ),
+            _ => self.format.auth_style(),
+        }
+    }
+
+    fn is_local(&self) -> bool {
+        self.local
+            .unwrap_or_else(|| is_local_url(&self.base_url))
+    }
+
+    fn customize_body(&self, body: &mut serde_json::Value) {
+        if let (Some(extra), Some(body)) = (&self.body, body.as_object_mut()) {
+            for (key, value) in extra {
+                body.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Reads the providers declared in the `[providers]` section of the config file.
+///
+/// Invalid declarations are reported and skipped.
+///
+/// # Returns
+///
+/// * `Vec<CustomProvider>` in the order of the config file.
+pub fn custom_providers() -> Vec<CustomProvider> {
+    let Ok(config) = config::get_config() else {
+        return Vec::new();
+    };
+    let Some(section) = config.get(PROVIDERS_SECTION).and_then(|v| v.as_table()) else {
+        return Vec::new();
+    };
+    section
+        .iter()
+        .filter_map(|(name, value)| match value.clone().try_into::<CustomProvider>() {
+            Ok(mut provider) => {
+                provider.name = name.clone();
+                Some(provider)
+            }
+            Err(e) => {
+                eprintln!("Warning: invalid provider '{}' in config: {}", name, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Selects the provider of the AI configuration.
+///
+/// The `provider` key of the ai section selects a provider by name, otherwise the model name
+/// is matched against the user-defined providers first, then the built-in ones. Models that
+/// match no provider are sent to the configured URL as an OpenAI-compatible service.
+///
+/// # Returns
+///
+/// * `Ok(Box<dyn Provider>)` to talk to.
+/// * `Err(Box<dyn Error>)` if the configured provider is unknown.
+pub fn resolve(ai: &AiConfig) -> Result<Box<dyn Provider>, Box<dyn Error>> {
+    let custom = custom_providers();
+    if let Some(name) = ai.provider.as_deref().filter(|name| !name.is_empty()) {
+        if let Some(provider) = custom.into_iter().find(|p| p.name == name) {
+            return Ok(Box::new(provider));
+        }
+        if let Some(provider) = BUILTIN_PROVIDERS.iter().find(|p| p.name == name) {
+            return Ok(Box::new(*provider));
+        }
+        return Err(format!(
+            "unknown ai provider '{}', supported providers: {}",
+            name,
+            provider_names().join(", ")
+        )
+        .into());
+    }
+    Ok(find_by_model(custom, &ai.model))
+}
+
+/// Returns the provider serving the model, or the generic OpenAI-compatible one.
+///
+/// # Arguments
+///
+/// * `model` - The name of the AI model.
+///
+/// # Returns
+///
+/// * `Box<dyn Provider>` serving the model.
+pub fn provider_for_model(model: &str) -> Box<dyn Provider> {
+    find_by_model(custom_providers(), model)
+}
+
+/// Returns the names of the user-defined and built-in providers.
+pub fn provider_names() -> Vec<String> {
+    custom_providers()
+        .iter()
+        .map(|p| p.name.clone())
+        .chain(BUILTIN_PROVIDERS.iter().map(|p| p.name.to_string()))
+        .collect()
+}
+
+/// Returns the help of the `--provider` options, listing the built-in providers.
+///
+/// The user-defined providers are not listed, the config file is not read to build the CLI.
+pub fn provider_help() -> String {
+    let names: Vec<&str> = BUILTIN_PROVIDERS.iter().map(|p| p.name).collect();
+    format!(
+        "the ai provider ({} or a [{}] entry), inferred from the model name when not set",
+        names.join(", "),
+        PROVIDERS_SECTION
+    )
+}
+
+fn find_by_model(custom: Vec<CustomProvider>, model: &str) -> Box<dyn Provider> {
+    if let Some(provider) = custom.into_iter().find(|p| p.matches(model)) {
+        print_verbose(&format!("Use provider '{}' for model '{}'", provider.name, model));
+        return Box::new(provider);
+    }
+    if let Some(provider) = BUILTIN_PROVIDERS.iter().find(|p| p.matches(model)) {
+        print_verbose(&format!("Use provider '{}' for model '{}'", provider.name, model));
+        return Box::new(*provider);
+    }
+    Box::new(GENERIC_PROVIDER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BUILTIN_PROVIDERS, CustomProvider, GENERIC_PROVIDER, find_by_model};
+    use crate::cli::provider::{ApiFormat, AuthStyle, Provider};
+
+    #[test]
+    fn test_find_by_model() {
+        let provider = find_by_model(Vec::new(), "glm-4-flash");
+        assert_eq!(provider.name(), "glm");
+        assert_eq!(
+            provider.endpoint("").unwrap(),
+            "https://open.bigmodel.cn/api/paas/v4/chat/completions"
+        );
+        // A user URL is completed with the provider path instead of the OpenAI one
+        assert_eq!(
+            provider.endpoint("https://open.bigmodel.cn/api/paas/v4").unwrap(),
+            "https://open.bigmodel.cn/api/paas/v4/chat/completions"
+        );
+
+        let provider = find_by_model(Vec::new(), "gemini-2.5-flash");
+        assert_eq!(
+            provider.endpoint("https://generativelanguage.googleapis.com/v1beta/openai/")
+                .unwrap(),
+            "https://generativelanguage.googleapis.com/v1beta/openai/chat/completions"
+        );
+
+        let provider = find_by_model(Vec::new(), "my-finetune");
+        assert_eq!(provider.name(), GENERIC_PROVIDER.name());
+        assert!(provider.endpoint("").is_none());
+    }
+
+    #[test]
+    fn test_builtin_quirks() {
+        let qwen = BUILTIN_PROVIDERS.iter().find(|p| p.name() == "qwen").unwrap();
+        let body = qwen.request_body("qwen3-8b", None, "hi".into(), false).unwrap();
+        assert_eq!(body["enable_thinking"], false);
+
+        // Self-hosted models behind the generic provider keep thinking disabled too
+        let body = GENERIC_PROVIDER
+            .request_body("qwen3-8b-local", None, "hi".into(), false)
+            .unwrap();
+        assert_eq!(body["extra_body"]["enable_thinking"], false);
+        assert!(body.get("enable_thinking").is_none());
+
+        let ollama = BUILTIN_PROVIDERS.iter().find(|p| p.name() == "ollama").unwrap();
+        let body = ollama.request_body("ollama/llama3.2", None, "hi".into(), false).unwrap();
+        assert_eq!(body["model"], "llama3.2");
+        assert!(ollama.is_local());
+    }
+
+    #[test]
+    fn test_custom_provider() {
+        let value: toml::Value = toml::from_str(
+            r#"
+            base_url = "https://llm.example.com"
+            path = "/openai/deployments/chat/completions"
+            auth = "header"
+            auth_header = "api-key"
+            model_prefixes = ["corp-"]
+            body = { max_tokens = 512 }
+            "#,
+        )
+        .unwrap();
+        let provider: CustomProvider = value.try_into().unwrap();
+        assert_eq!(provider.format(), ApiFormat::OpenAi);
+        assert_eq!(provider.auth_style(), AuthStyle::Header("api-key".to_string()));
+        assert!(find_by_model(vec![provider.clone()], "corp-large").matches("corp-large"));
+
+        let body = provider.request_body("corp-large", None, "hi".into(), false).unwrap();
+        assert_eq!(body["max_tokens"], 512);
+    }
+}
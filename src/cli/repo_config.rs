@@ -0,0 +1,300 @@
+use gim_config::{config, directory};
+use lazy_static::lazy_static;
+use std::{
+    env, fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+use toml::Value;
+
+use crate::cli::{ai_profile::PROFILE_KEYS, verbose::print_verbose};
+
+/// The repository configuration file, see [`repo_config_path`].
+pub const REPO_CONFIG_FILE: &str = ".gim.toml";
+
+/// The sections a repository configuration may override.
+///
+/// Other sections, such as `[providers]`, are only read from the global config file, so that
+/// a repository cannot send the user's api keys to another server. For the same reason the
+/// `[secrets]` section of a repository can only make the scanning stricter, see [`secret`],
+/// and its `[trailers]` section cannot sign off the commits of the user, see [`trailer`].
+///
+/// [`secret`]: crate::cli::secret
+/// [`trailer`]: crate::cli::trailer
+pub const REPO_SECTIONS: [&str; 8] = [
+    "ai", "user", "prompt", "secrets", "lint", "scope", "ticket", "trailers",
+];
+
+/// Returns the repository configuration file, found by [`find_in_repo`].
+///
+/// # Returns
+///
+/// * `Some(PathBuf)` of the nearest `.gim.toml` file.
+/// * `None` if there is none, or the current directory is not in a git repository.
+pub fn repo_config_path() -> Option<PathBuf> {
+    find_in_repo(Path::new(REPO_CONFIG_FILE))
+}
+
+/// Searches a file from the current directory up to the root of the git repository.
+///
+/// # Arguments
+///
+/// * `relative_path` - The path of the file, relative to the searched directories.
+///
+/// # Returns
+///
+/// * `Some(PathBuf)` of the nearest file.
+/// * `None` if there is none, or the current directory is not in a git repository.
+pub fn find_in_repo(relative_path: &Path) -> Option<PathBuf> {
+    let current_dir = env::current_dir().ok()?;
+    for dir in current_dir.ancestors() {
+        let path = dir.join(relative_path);
+        if path.is_file() {
+            return Some(path);
+        }
+        // `.git` is a directory in a repository and a file in a worktree or a submodule
+        if dir.join(".git").exists() {
+            return None;
+        }
+    }
+    None
+}
+
+lazy_static! {
+    // The configuration is looked up many times in a run
+    static ref REPO_CONFIG: Option<(PathBuf, Value)> = read_repo_config();
+}
+
+/// Returns the repository configuration file, keeping only the sections it may override.
+///
+/// An invalid file is reported and ignored.
+///
+/// # Returns
+///
+/// * `Some((PathBuf, Value))` containing the file path and its content.
+/// * `None` if there is no valid repository configuration file.
+pub fn get_repo_config() -> Option<(PathBuf, Value)> {
+    REPO_CONFIG.clone()
+}
+
+fn read_repo_config() -> Option<(PathBuf, Value)> {
+    let path = repo_config_path()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Warning: failed to read '{}': {}", path.display(), e);
+            return None;
+        }
+    };
+    let mut repo_config: Value = match toml::from_str(&content) {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("Warning: ignore invalid '{}': {}", path.display(), e);
+            return None;
+        }
+    };
+    if let Some(table) = repo_config.as_table_mut() {
+        table.retain(|section, _| {
+            let allowed = REPO_SECTIONS.contains(&section);
+            if !allowed {
+                eprintln!(
+                    "Warning: section '{}' is not allowed in '{}', ignored",
+                    section,
+                    path.display()
+                );
+            }
+            allowed
+        });
+    }
+    print_verbose(&format!("Use repository config '{}'", path.display()));
+    Some((path, repo_config))
+}
+
+/// Returns the effective configuration: the global config file with the repository
+/// configuration file merged over it.
+///
+/// # Returns
+///
+/// * `Ok(Value)` containing the effective configuration.
+/// * `Err(std::io::Error)` if the global config file cannot be read.
+pub fn get_config() -> Result<Value> {
+    let mut config = config::get_config()?;
+    if let Some((_, repo_config)) = get_repo_config() {
+        merge_config(&mut config, &repo_config);
+    }
+    Ok(config)
+}
+
+/// Retrieves a value from the effective configuration.
+///
+/// # Arguments
+///
+/// * `section` - The section name in the configuration.
+/// * `key` - The key name within the section.
+///
+/// # Returns
+///
+/// * `Ok(Value)` containing the requested value.
+/// * `Err(std::io::Error)` if the section or the key doesn't exist.
+pub fn get_config_value(section: &str, key: &str) -> Result<Value> {
+    let config = get_config()?;
+    let section_table = config.get(section).ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("Section '{}' not found", section),
+        )
+    })?;
+    section_table.get(key).cloned().ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("Key '{}' not found in section '{}'", key, section),
+        )
+    })
+}
+
+/// Merges the repository configuration over the global one, table by table.
+///
+/// When the repository sets the model, the url, the api key or the provider of the ai
+/// section, none of them is inherited from the global config: the global api key is never
+/// sent to a model or a url chosen by the repository.
+fn merge_config(global: &mut Value, repo: &Value) {
+    let (Some(global), Some(repo)) = (global.as_table_mut(), repo.as_table()) else {
+        return;
+    };
+    for (section, repo_section) in repo {
+        let Some(repo_section) = repo_section.as_table() else {
+            global.insert(section.clone(), repo_section.clone());
+            continue;
+        };
+        let global_section = global
+            .entry(section.clone())
+            .or_insert_with(|| Value::Table(Default::default()));
+        let Some(global_section) = global_section.as_table_mut() else {
+            *global_section = Value::Table(repo_section.clone());
+            continue;
+        };
+        if section == "ai" && PROFILE_KEYS.iter().any(|key| repo_section.contains_key(*key)) {
+            for key in PROFILE_KEYS {
+                global_section.remove(key);
+            }
+            // The url is optional, the default url of the model is used instead
+            global_section.insert("url".to_string(), Value::String(String::new()));
+        }
+        for (key, value) in repo_section {
+            global_section.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+/// Prints the config file locations and each effective value with the file it comes from.
+///
+/// # Returns
+///
+/// * `Ok(())` if the configuration was printed.
+/// * `Err(std::io::Error)` if the global config file cannot be read.
+pub fn print_config_sources() -> Result<()> {
+    let mut config = config::get_config_and_print()?;
+    let global_path = directory::config_dir()?.join("config.toml");
+    let repo = get_repo_config();
+    match &repo {
+        Some((path, _)) => println!("Repository config file is {}", path.display()),
+        None => println!(
+            "No repository config file, add a '{}' file to the repository to override the global config",
+            REPO_CONFIG_FILE
+        ),
+    }
+    let global = config.clone();
+    if let Some((_, repo_config)) = &repo {
+        merge_config(&mut config, repo_config);
+    }
+
+    println!("Effective values:");
+    let Some(sections) = config.as_table() else {
+        return Ok(());
+    };
+    for (section, values) in sections {
+        let Some(values) = values.as_table() else {
+            continue;
+        };
+        for (key, value) in values {
+            let in_file =
+                |file: &Value| file.get(section).and_then(|s| s.get(key)) == Some(value);
+            let source = match &repo {
+                Some((path, repo_config)) if in_file(repo_config) => path.display().to_string(),
+                _ if in_file(&global) => global_path.display().to_string(),
+                _ => "default".to_string(),
+            };
+            let value = if key == "apikey" {
+                mask(value)
+            } else {
+                value.to_string()
+            };
+            println!("  {}.{} = {}  ({})", section, key, value, source);
+        }
+    }
+    Ok(())
+}
+
+fn mask(value: &Value) -> String {
+    match value.as_str() {
+        Some(key) if key.chars().count() > 8 => {
+            let prefix: String = key.chars().take(4).collect();
+            format!("\"{}****\"", prefix)
+        }
+        Some(key) if !key.is_empty() => "\"****\"".to_string(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_config;
+
+    #[test]
+    fn test_merge_config() {
+        let mut global: toml::Value = toml::from_str(
+            r#"
+            [update]
+            tried = 0
+            [ai]
+            model = "gpt-4o-mini"
+            apikey = "sk-openai"
+            language = "English"
+            [user]
+            lines_limit = 1000
+            "#,
+        )
+        .unwrap();
+        let repo: toml::Value = toml::from_str(
+            r#"
+            [user]
+            lines_limit = 3000
+            [ai]
+            language = "Chinese"
+            [prompt]
+            subject = "Use the Conventional Commits format"
+            "#,
+        )
+        .unwrap();
+        merge_config(&mut global, &repo);
+        assert_eq!(global["ai"]["model"].as_str(), Some("gpt-4o-mini"));
+        assert_eq!(global["ai"]["apikey"].as_str(), Some("sk-openai"));
+        assert_eq!(global["ai"]["language"].as_str(), Some("Chinese"));
+        assert_eq!(global["user"]["lines_limit"].as_integer(), Some(3000));
+        assert_eq!(global["update"]["tried"].as_integer(), Some(0));
+        assert!(global["prompt"]["subject"].is_str());
+
+        // The global api key is not sent to a model chosen by the repository
+        let repo: toml::Value = toml::from_str(
+            r#"
+            [ai]
+            model = "ollama/llama3.2"
+            "#,
+        )
+        .unwrap();
+        merge_config(&mut global, &repo);
+        assert_eq!(global["ai"]["model"].as_str(), Some("ollama/llama3.2"));
+        assert!(global["ai"].get("apikey").is_none());
+        assert_eq!(global["ai"]["language"].as_str(), Some("Chinese"));
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    process::Command,
+};
+
+use crate::cli::verbose::print_verbose;
+
+const EDIT_MESSAGE_FILE: &str = "GIM_EDITMSG";
+
+/// The choice made by the user after reviewing the generated commit message.
+pub enum ReviewAction {
+    /// Commit the message as it is.
+    Accept,
+    /// Open the message in the editor before committing.
+    Edit,
+    /// Ask the AI for a new message, with an optional hint.
+    Regenerate(Option<String>),
+    /// Leave without committing anything.
+    Abort,
+}
+
+/// Asks the user what to do with the generated commit message.
+///
+/// Keeps asking until a valid answer is given. End of input is treated as an abort.
+///
+/// # Returns
+///
+/// * `ReviewAction` selected by the user.
+pub fn ask_review_action() -> ReviewAction {
+    loop {
+        let answer = match read_line(
+            "Commit with this message? [Y]es / [e]dit / [r]egenerate / [a]bort: ",
+        ) {
+            Some(answer) => answer,
+            None => return ReviewAction::Abort,
+        };
+        match answer.to_lowercase().as_str() {
+            "" | "y" | "yes" => return ReviewAction::Accept,
+            "e" | "edit" => return ReviewAction::Edit,
+            "r" | "regenerate" => {
+                let hint = read_line("Hint for the AI (optional, press Enter to skip): ")
+                    .filter(|hint| !hint.is_empty());
+                return ReviewAction::Regenerate(hint);
+            }
+            "a" | "abort" | "q" | "quit" => return ReviewAction::Abort,
+            _ => eprintln!("Unknown answer '{}'", answer),
+        }
+    }
+}
+
+/// Opens the commit message in the user's editor and returns the edited subject and body.
+///
+/// The editor is resolved by `git var GIT_EDITOR`, which honors `$GIT_EDITOR`, `core.editor`,
+/// `$VISUAL` and `$EDITOR`. Lines starting with `#` are dropped from the result.
+///
+/// # Arguments
+///
+/// * `subject` - The current commit subject.
+/// * `body` - The current commit body.
+///
+/// # Returns
+///
+/// * `Ok((String, String))` containing the edited subject and body.
+/// * `Err(Box<dyn Error>)` if the editor fails or the message is left empty.
+pub fn edit_message(
+    subject: &str,
+    body: &str,
+) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let path = edit_message_path()?;
+    fs::write(
+        &path,
+        format!(
+            "{}\n\n{}\n\n# Edit the commit message above. Lines starting with '#' will be ignored.\n# The first line is the commit subject, the rest is the commit message.\n",
+            subject, body
+        ),
+    )?;
+
+    let editor = Command::new("git").args(["var", "GIT_EDITOR"]).output()?;
+    let editor = String::from_utf8_lossy(&editor.stdout).trim().to_string();
+    let editor = if editor.is_empty() {
+        "vi".to_string()
+    } else {
+        editor
+    };
+    print_verbose(&format!(
+        "Open '{}' with editor '{}'",
+        path.display(),
+        editor
+    ));
+
+    let status = editor_command(&editor).arg(&path).status()?;
+    if !status.success() {
+        return Err(format!("editor '{}' exited with {}", editor, status).into());
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let _ = fs::remove_file(&path);
+    parse_edited_message(&content).ok_or_else(|| "commit message is empty".into())
+}
+
+/// Builds the command running the editor through the shell, as git does, so that editors
+/// with quoted paths or arguments work. The file to edit is the argument to add.
+fn editor_command(editor: &str) -> Command {
+    #[cfg(target_os = "windows")]
+    {
+        let mut command = Command::new("cmd");
+        command.args(["/C", editor]);
+        command
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let mut command = Command::new("sh");
+        command.args(["-c", &format!("{} \"$@\"", editor), editor]);
+        command
+    }
+}
+
+fn edit_message_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", EDIT_MESSAGE_FILE])
+        .output()?;
+    if !output.status.success() {
+        return Err("failed to locate git directory".into());
+    }
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim(),
+    ))
+}
+
+fn parse_edited_message(content: &str) -> Option<(String, String)> {
+    let lines: Vec<&str> = content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect();
+    let text = lines.join("\n");
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let (subject, body) = text.split_once('\n').unwrap_or((text, ""));
+    Some((subject.trim().to_string(), body.trim().to_string()))
+}
+
+fn read_line(question: &str) -> Option<String> {
+    print!("{}", question);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    match io::stdin().lock().read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::editor_command;
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_editor_command() {
+        let path = std::env::temp_dir().join("gim_editor_test.txt");
+        std::fs::write(&path, "old message").unwrap();
+        // Quoted arguments are kept as they are, as in `emacsclient -a ''`
+        let status = editor_command("sed -i 's/old message/new message/'")
+            .arg(&path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new message");
+        let _ = std::fs::remove_file(&path);
+    }
+}
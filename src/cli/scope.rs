@@ -0,0 +1,293 @@
+use regex::Regex;
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+use toml::Value;
+
+use crate::cli::{diff, repo_config, verbose::print_verbose};
+
+/// The config section of the scope inference.
+pub const SCOPE_SECTION: &str = "scope";
+/// The names of the sources of scopes, in the order they are tried by default.
+pub const DEFAULT_SOURCES: [&str; 3] = ["paths", "cargo", "npm"];
+/// The names of all the sources of scopes.
+pub const SOURCES: [&str; 4] = ["paths", "cargo", "npm", "directory"];
+
+/// A source mapping the changed paths to scopes.
+#[derive(Debug)]
+pub enum Source {
+    /// Globs mapped to scopes, from the `[scope.paths]` config table.
+    Paths(Vec<(Regex, String)>),
+    /// Package directories mapped to package names, such as the Cargo workspace members.
+    Packages(Vec<(String, String)>),
+    /// The top-level directory of the path.
+    Directory,
+}
+
+impl Source {
+    fn scope_of(&self, path: &str) -> Option<String> {
+        match self {
+            Source::Paths(globs) => globs
+                .iter()
+                .find(|(glob, _)| glob.is_match(path))
+                .map(|(_, scope)| scope.clone()),
+            // The deepest package holding the path
+            Source::Packages(packages) => packages
+                .iter()
+                .filter(|(dir, _)| path.starts_with(&format!("{}/", dir)))
+                .max_by_key(|(dir, _)| dir.len())
+                .map(|(_, name)| name.clone()),
+            Source::Directory => path.split_once('/').map(|(dir, _)| dir.to_string()),
+        }
+    }
+}
+
+/// Infers the scope of the changes from the changed paths, according to the `[scope]`
+/// config section.
+///
+/// # Arguments
+///
+/// * `amend` - Whether the changes of the last commit are described.
+///
+/// # Returns
+///
+/// * `Some(String)` containing the scope, when all the files with a scope share it.
+/// * `None` if no file has a scope, or the files have different scopes.
+pub fn infer_scope(amend: bool) -> Option<String> {
+    let sources = load_sources();
+    if sources.is_empty() {
+        return None;
+    }
+    let mut paths: Vec<String> = diff::git_output(&["diff", "--cached", "--name-only"])
+        .lines()
+        .map(str::to_string)
+        .collect();
+    if amend {
+        let last = diff::git_output(&["show", "--pretty=format:", "--name-only", "HEAD"]);
+        paths.extend(last.lines().map(str::to_string));
+    }
+    let scope = resolve(&paths, &sources);
+    print_verbose(&format!("Inferred scope: {:?}", scope));
+    scope
+}
+
+/// Maps every path to the scope of the first source knowing it.
+///
+/// # Returns
+///
+/// * `Some(String)` containing the scope, when all the paths with a scope share it.
+/// * `None` if no path has a scope, or the paths have different scopes.
+pub fn resolve(paths: &[String], sources: &[Source]) -> Option<String> {
+    let scopes: BTreeSet<String> = paths
+        .iter()
+        .filter(|path| !path.trim().is_empty())
+        .filter_map(|path| sources.iter().find_map(|source| source.scope_of(path)))
+        .collect();
+    if scopes.len() > 1 {
+        print_verbose(&format!("The changes have several scopes: {:?}", scopes));
+    }
+    match scopes.len() {
+        1 => scopes.into_iter().next(),
+        _ => None,
+    }
+}
+
+/// Converts a glob relative to the root of the repository into a regular expression.
+///
+/// `**` matches any count of directories, `*` and `?` match within a path component.
+pub fn glob_to_regex(glob: &str) -> Option<Regex> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.trim_start_matches('/').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("(?:.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    // A directory matches everything under it
+    pattern.push_str("(?:/.*)?$");
+    match Regex::new(&pattern) {
+        Ok(regex) => Some(regex),
+        Err(e) => {
+            eprintln!("Warning: invalid scope glob '{}': {}", glob, e);
+            None
+        }
+    }
+}
+
+fn load_sources() -> Vec<Source> {
+    let section = repo_config::get_config()
+        .ok()
+        .and_then(|config| config.get(SCOPE_SECTION).cloned());
+    let names: Vec<String> = match section.as_ref().and_then(|s| s.get("sources")) {
+        Some(Value::Array(names)) => names
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => DEFAULT_SOURCES.iter().map(|name| name.to_string()).collect(),
+    };
+    let root = PathBuf::from(diff::git_output(&["rev-parse", "--show-toplevel"]));
+    names
+        .iter()
+        .filter_map(|name| match name.as_str() {
+            "paths" => {
+                let table = section.as_ref()?.get("paths")?.as_table()?;
+                let globs = table
+                    .iter()
+                    .filter_map(|(glob, scope)| Some((glob_to_regex(glob)?, scope.as_str()?.to_string())))
+                    .collect();
+                Some(Source::Paths(globs))
+            }
+            "cargo" => Some(Source::Packages(cargo_members(&root))),
+            "npm" => Some(Source::Packages(npm_workspaces(&root))),
+            "directory" => Some(Source::Directory),
+            _ => {
+                eprintln!(
+                    "Warning: unknown scope source '{}', expected one of: {}",
+                    name,
+                    SOURCES.join(", ")
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+/// Returns the directories of the Cargo workspace members with their package names.
+fn cargo_members(root: &Path) -> Vec<(String, String)> {
+    let Some(manifest) = read_toml(&root.join("Cargo.toml")) else {
+        return Vec::new();
+    };
+    let members = manifest
+        .get("workspace")
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(Value::as_array)
+        .map(|members| members.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
+    expand_dirs(root, &members)
+        .into_iter()
+        .filter_map(|dir| {
+            let member = read_toml(&root.join(&dir).join("Cargo.toml"))?;
+            let name = member.get("package")?.get("name")?.as_str()?.to_string();
+            Some((dir, name))
+        })
+        .collect()
+}
+
+/// Returns the directories of the npm workspaces with their package names, without the
+/// organization such as `@acme/`.
+fn npm_workspaces(root: &Path) -> Vec<(String, String)> {
+    let Some(package) = read_json(&root.join("package.json")) else {
+        return Vec::new();
+    };
+    let workspaces = match package.get("workspaces") {
+        Some(serde_json::Value::Array(workspaces)) => workspaces,
+        Some(serde_json::Value::Object(workspaces)) => match workspaces.get("packages") {
+            Some(serde_json::Value::Array(workspaces)) => workspaces,
+            _ => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+    let workspaces: Vec<&str> = workspaces.iter().filter_map(|w| w.as_str()).collect();
+    expand_dirs(root, &workspaces)
+        .into_iter()
+        .filter_map(|dir| {
+            let package = read_json(&root.join(&dir).join("package.json"))?;
+            let name = package.get("name")?.as_str()?;
+            let name = name.rsplit('/').next().unwrap_or(name).to_string();
+            Some((dir, name))
+        })
+        .collect()
+}
+
+/// Expands workspace patterns such as `crates/*` into the directories they match.
+fn expand_dirs(root: &Path, patterns: &[&str]) -> Vec<String> {
+    let mut dirs = Vec::new();
+    for pattern in patterns {
+        let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+        if !pattern.contains(['*', '?']) {
+            dirs.push(pattern.to_string());
+            continue;
+        }
+        // Only the last component may be a glob, as in most workspaces
+        let (parent, _) = pattern.rsplit_once('/').unwrap_or(("", pattern));
+        let Some(glob) = glob_to_regex(pattern) else {
+            continue;
+        };
+        let Ok(entries) = fs::read_dir(root.join(parent)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let dir = if parent.is_empty() {
+                name
+            } else {
+                format!("{}/{}", parent, name)
+            };
+            if entry.path().is_dir() && glob.is_match(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+    dirs
+}
+
+fn read_toml(path: &Path) -> Option<Value> {
+    toml::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn read_json(path: &Path) -> Option<serde_json::Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Source, glob_to_regex, resolve};
+
+    #[test]
+    fn test_glob_to_regex() {
+        let glob = glob_to_regex("crates/parser/**").unwrap();
+        assert!(glob.is_match("crates/parser/src/lib.rs"));
+        assert!(!glob.is_match("crates/parsers/src/lib.rs"));
+        let glob = glob_to_regex("**/*.proto").unwrap();
+        assert!(glob.is_match("api.proto"));
+        assert!(glob.is_match("api/v1/user.proto"));
+        assert!(glob_to_regex("docs").unwrap().is_match("docs/index.md"));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let sources = vec![
+            Source::Paths(vec![(glob_to_regex("docs/**").unwrap(), "docs".to_string())]),
+            Source::Packages(vec![
+                ("crates/parser".to_string(), "parser".to_string()),
+                ("crates/cli".to_string(), "cli".to_string()),
+            ]),
+        ];
+        let paths = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            resolve(&paths(&["crates/parser/src/lib.rs", "Cargo.lock"]), &sources),
+            Some("parser".to_string())
+        );
+        assert_eq!(
+            resolve(&paths(&["crates/parser/src/lib.rs", "crates/cli/src/main.rs"]), &sources),
+            None
+        );
+        assert_eq!(resolve(&paths(&["docs/guide.md"]), &sources), Some("docs".to_string()));
+        assert_eq!(resolve(&paths(&["README.md"]), &sources), None);
+    }
+}
@@ -0,0 +1,211 @@
+use futures::{StreamExt, TryStreamExt, stream};
+
+use crate::cli::{
+    ai_configer::AiConfig, custom_param::get_concurrency, diff::FileChange,
+    http::chat_with_fallback, verbose::print_verbose,
+};
+
+/// Summarizes chunks of the changes concurrently and joins their summaries in order.
+///
+/// Every chunk is sent with the same diff prompt, at most `concurrency` requests at the same
+/// time. Streaming is turned off, as the concurrent answers would be rendered mixed up.
+///
+/// # Arguments
+///
+/// * `ai` - The AI configuration, with its fallback profiles.
+/// * `system` - The rendered diff prompt.
+/// * `chunks` - The chunks of the changes.
+/// * `instructions` - The instructions appended to every chunk, such as the answer language.
+/// * `verbose` - Whether to print verbose log information.
+///
+/// # Returns
+///
+/// * `Ok(String)` containing the summaries of the chunks, one after another.
+/// * `Err(Box<dyn Error>)` if a chunk could not be summarized by any profile.
+pub async fn summarize_chunks(
+    ai: &AiConfig,
+    system: &str,
+    chunks: &[String],
+    instructions: &str,
+    verbose: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let concurrency = get_concurrency();
+    eprintln!(
+        "Summarizing the changes in {} chunks, {} at a time...",
+        chunks.len(),
+        concurrency
+    );
+    let quiet = without_stream(ai);
+    let profiles = quiet.profiles();
+    let summaries: Vec<(String, usize)> = stream::iter(chunks.iter().enumerate().map(|(i, chunk)| {
+        print_verbose(&format!("Summarize chunk {}/{}", i + 1, chunks.len()));
+        chat_with_fallback(
+            &profiles,
+            Some(system.to_string()),
+            format!("{}{}", chunk, instructions),
+            verbose,
+        )
+    }))
+    .buffered(concurrency)
+    .try_collect()
+    .await?;
+
+    Ok(summaries
+        .iter()
+        .map(|(summary, _)| summary.trim())
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Summarizes every file in its own request, concurrently, into one
+/// `FILE: CHANGES (CHANGED_LINES_COUNT)` line per file, in the order of the files.
+///
+/// The path and the changed lines count are filled in from git, only the description comes
+/// from the AI. Deleted and ignored files need no request, and a file that could not be
+/// summarized gets a generic description instead of failing the whole message.
+///
+/// # Arguments
+///
+/// * `ai` - The AI configuration, with its fallback profiles.
+/// * `system` - The rendered diff prompt.
+/// * `files` - The changes of the files.
+/// * `instructions` - The instructions appended to every file, such as the answer language.
+/// * `verbose` - Whether to print verbose log information.
+///
+/// # Returns
+///
+/// * `Ok(String)` containing the lines of the files.
+/// * `Err(Box<dyn Error>)` if no file could be summarized at all.
+pub async fn summarize_files(
+    ai: &AiConfig,
+    system: &str,
+    files: &[FileChange],
+    instructions: &str,
+    verbose: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let concurrency = get_concurrency();
+    eprintln!(
+        "Summarizing {} files, {} at a time...",
+        files.len(),
+        concurrency
+    );
+    let quiet = without_stream(ai);
+    let profiles = quiet.profiles();
+    // None for the deleted and ignored files, which are not sent
+    let answers: Vec<Option<Result<String, String>>> =
+        stream::iter(files.iter().map(|file| async {
+            if !is_sent(file) {
+                return None;
+            }
+            print_verbose(&format!("Summarize file '{}'", file.path));
+            let answer = chat_with_fallback(
+                &profiles,
+                Some(system.to_string()),
+                format!("{}{}", file.content, instructions),
+                verbose,
+            )
+            .await;
+            Some(answer.map(|(answer, _)| answer).map_err(|e| e.to_string()))
+        }))
+        .buffered(concurrency)
+        .collect()
+        .await;
+
+    let mut lines = Vec::new();
+    let mut first_error = None;
+    let mut failed = 0;
+    for (file, answer) in files.iter().zip(answers) {
+        let description = match answer {
+            None if file.status.starts_with('D') => "Delete file".to_string(),
+            None => fallback_description(&file.status),
+            Some(Ok(answer)) => clean_description(&answer),
+            Some(Err(e)) => {
+                eprintln!("Warning: failed to summarize '{}' - {}", file.path, e);
+                failed += 1;
+                first_error.get_or_insert(e);
+                fallback_description(&file.status)
+            }
+        };
+        lines.push(format!("{}: {} ({})", file.path, description, file.changed_lines));
+    }
+    if let Some(e) = first_error
+        && failed == files.iter().filter(|file| is_sent(file)).count()
+    {
+        return Err(e.into());
+    }
+    Ok(lines.join("\n"))
+}
+
+fn is_sent(file: &FileChange) -> bool {
+    !file.status.starts_with('D') && !file.ignored
+}
+
+/// Keeps the description of a file from the answer of the AI: its first line, without the
+/// path and the changed lines count the AI may have written.
+fn clean_description(answer: &str) -> String {
+    let line = answer
+        .lines()
+        .map(|line| line.trim().trim_matches(|c| matches!(c, '{' | '}' | '`' | '-' | '*' | ' ')))
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    // The path may be written differently, any leading word followed by a colon is left out
+    let line = match line.split_once(':') {
+        Some((path, rest)) if !path.contains(char::is_whitespace) => rest.trim(),
+        _ => line,
+    };
+    let line = match line.rsplit_once('(') {
+        Some((description, count))
+            if count
+                .trim_end_matches(')')
+                .trim()
+                .chars()
+                .all(|c| c.is_ascii_digit()) =>
+        {
+            description.trim()
+        }
+        _ => line,
+    };
+    line.trim_end_matches(':').trim().to_string()
+}
+
+/// Describes a file by its status when the AI could not summarize it.
+fn fallback_description(status: &str) -> String {
+    match status.chars().next() {
+        Some('A') => "Add file",
+        Some('R') => "Rename file",
+        Some('C') => "Copy file",
+        _ => "Modify file",
+    }
+    .to_string()
+}
+
+/// Returns a copy of the AI configuration with streaming turned off, fallbacks included.
+fn without_stream(ai: &AiConfig) -> AiConfig {
+    let mut quiet = ai.clone();
+    quiet.stream = false;
+    for fallback in quiet.fallbacks.iter_mut() {
+        fallback.stream = false;
+    }
+    quiet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::clean_description;
+
+    #[test]
+    fn test_clean_description() {
+        assert_eq!(
+            clean_description("main.rs: Add login validation (87)"),
+            "Add login validation"
+        );
+        assert_eq!(
+            clean_description("\n{Add login validation}\nmore text"),
+            "Add login validation"
+        );
+        assert_eq!(
+            clean_description("Support (optional) flags: a, b"),
+            "Support (optional) flags: a, b"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{
+    cli::{
+        breaking::{self, Finding},
+        diff, scope, ticket,
+        verbose::print_verbose,
+    },
+    constants::RECENT_COMMITS_COUNT,
+};
+
+/// The repository context available to the prompt templates.
+#[derive(Clone, Debug, Default)]
+pub struct PromptContext {
+    /// The answer language.
+    pub language: String,
+    /// The current branch, empty when HEAD is detached.
+    pub branch: String,
+    /// The ticket ids found in the branch name, such as `PROJ-1234`.
+    pub tickets: Vec<String>,
+    /// The subjects of the last commits, one per line.
+    pub recent_commits: String,
+    /// The count of changed files.
+    pub file_count: usize,
+    /// The count of added and removed lines.
+    pub changed_lines: usize,
+    /// The name of the directory of the repository.
+    pub repo_name: String,
+    /// The scope inferred from the changed paths, such as `parser`.
+    pub scope: String,
+    /// The removed or changed public items found in the changes.
+    pub breaking_changes: Vec<Finding>,
+}
+
+impl PromptContext {
+    /// Collects the context of the current repository.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - The answer language.
+    /// * `amend` - Whether the changes of the last commit are described.
+    pub fn collect(language: &str, amend: bool) -> Self {
+        let branch = diff::current_branch();
+        let (file_count, changed_lines) = diff::numstat(amend);
+        PromptContext {
+            language: language.to_string(),
+            tickets: ticket::find_tickets(&branch),
+            branch,
+            recent_commits: diff::recent_commits(RECENT_COMMITS_COUNT),
+            file_count,
+            changed_lines,
+            repo_name: diff::repo_name(),
+            scope: scope::infer_scope(amend).unwrap_or_default(),
+            breaking_changes: breaking::detect(amend),
+        }
+    }
+
+    /// Returns the value of a template variable, `None` if the variable is unknown.
+    pub fn get(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "language" => self.language.clone(),
+            "branch" => self.branch.clone(),
+            "ticket" => self.tickets.join(", "),
+            "recent_commits" => self.recent_commits.clone(),
+            "file_count" => self.file_count.to_string(),
+            "changed_lines" => self.changed_lines.to_string(),
+            "repo_name" => self.repo_name.clone(),
+            "scope" => self.scope.clone(),
+            "breaking_changes" => self
+                .breaking_changes
+                .iter()
+                .map(Finding::to_string)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// Checks whether a template uses a variable, as a value or in a condition.
+pub fn uses_variable(template: &str, name: &str) -> bool {
+    tokenize(template).iter().any(|token| match token {
+        Token::Tag(tag) => {
+            let tag = tag.trim();
+            let tag = tag
+                .strip_prefix("#if ")
+                .or_else(|| tag.strip_prefix("#unless "))
+                .unwrap_or(tag);
+            tag.trim() == name
+        }
+        Token::Text(_) => false,
+    })
+}
+
+/// Renders a prompt template.
+///
+/// `{{name}}` is replaced by the value of the variable, `{{#if name}}...{{else}}...{{/if}}`
+/// and `{{#unless name}}...{{/unless}}` keep their content depending on whether the variable
+/// is set: neither empty nor `0`. Unknown variables are kept as they are. A condition tag
+/// alone on its line is removed with its line.
+///
+/// # Arguments
+///
+/// * `template` - The prompt template.
+/// * `context` - The values of the variables.
+///
+/// # Returns
+///
+/// * `String` containing the rendered prompt.
+pub fn render(template: &str, context: &PromptContext) -> String {
+    let template = strip_standalone_tags(template);
+    let tokens = tokenize(&template);
+    let mut out = String::with_capacity(template.len());
+    let mut pos = 0;
+    while pos < tokens.len() {
+        // A closing tag without its opening one is kept as it is
+        if let Some(tag) = render_block(&tokens, &mut pos, context, true, &mut out) {
+            print_verbose(&format!("Unexpected '{{{{{}}}}}' in prompt template", tag));
+            out.push_str(&format!("{{{{{}}}}}", tag));
+        }
+    }
+    out
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Text(&'a str),
+    Tag(&'a str),
+}
+
+fn is_block_tag(tag: &str) -> bool {
+    let tag = tag.trim();
+    tag.starts_with('#') || tag.starts_with('/') || tag == "else"
+}
+
+fn tokenize(template: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        tokens.push(Token::Text(&rest[..start]));
+        tokens.push(Token::Tag(&rest[start + 2..start + 2 + end]));
+        rest = &rest[start + 2 + end + 2..];
+    }
+    tokens.push(Token::Text(rest));
+    tokens
+}
+
+/// Removes the line breaks and the indentation around the block tags alone on their line,
+/// so that conditions leave no blank lines.
+fn strip_standalone_tags(template: &str) -> String {
+    template
+        .split_inclusive('\n')
+        .map(|line| {
+            let tag = line.trim();
+            let standalone = tag.starts_with("{{")
+                && tag.ends_with("}}")
+                && tag.matches("{{").count() == 1
+                && is_block_tag(&tag[2..tag.len() - 2]);
+            if standalone { tag } else { line }
+        })
+        .collect()
+}
+
+/// Renders tokens until the end of the template or a closing tag of the current block.
+///
+/// # Returns
+///
+/// * `Some(&str)` containing the `else` or closing tag that ended the block.
+/// * `None` at the end of the template.
+fn render_block<'a>(
+    tokens: &[Token<'a>],
+    pos: &mut usize,
+    context: &PromptContext,
+    emit: bool,
+    out: &mut String,
+) -> Option<&'a str> {
+    while *pos < tokens.len() {
+        let token = &tokens[*pos];
+        *pos += 1;
+        let raw = match token {
+            Token::Text(text) => {
+                if emit {
+                    out.push_str(text);
+                }
+                continue;
+            }
+            Token::Tag(raw) => *raw,
+        };
+        let tag = raw.trim();
+        let condition = tag
+            .strip_prefix("#if ")
+            .map(|name| (name.trim(), false))
+            .or_else(|| tag.strip_prefix("#unless ").map(|name| (name.trim(), true)));
+        if let Some((name, negate)) = condition {
+            let set = context
+                .get(name)
+                .is_some_and(|value| !value.trim().is_empty() && value.trim() != "0");
+            let keep = set != negate;
+            if render_block(tokens, pos, context, emit && keep, out) == Some("else") {
+                render_block(tokens, pos, context, emit && !keep, out);
+            }
+        } else if tag == "else" || tag.starts_with('/') {
+            return Some(tag);
+        } else if emit {
+            match context.get(tag) {
+                Some(value) => out.push_str(&value),
+                None => out.push_str(&format!("{{{{{}}}}}", raw)),
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PromptContext, render, uses_variable};
+
+    #[test]
+    fn test_render() {
+        let context = PromptContext {
+            language: "Chinese".into(),
+            branch: "feature/PROJ-1234-login".into(),
+            tickets: vec!["PROJ-1234".into()],
+            file_count: 3,
+            ..Default::default()
+        };
+        let template = "Answer in {{language}} for {{ file_count }} files.\n\
+            {{#if ticket}}\n\
+            Mention {{ticket}}.\n\
+            {{else}}\n\
+            No ticket.\n\
+            {{/if}}\n\
+            {{#unless recent_commits}}\n\
+            No history.\n\
+            {{/unless}}\n\
+            Format {FILE: CHANGES} {{unknown}}";
+        assert_eq!(
+            render(template, &context),
+            "Answer in Chinese for 3 files.\nMention PROJ-1234.\nNo history.\nFormat {FILE: CHANGES} {{unknown}}"
+        );
+
+        let context = PromptContext::default();
+        assert_eq!(
+            render("{{#if ticket}}Refs {{ticket}}{{else}}none{{/if}}, {{changed_lines}}", &context),
+            "none, 0"
+        );
+    }
+
+    #[test]
+    fn test_uses_variable() {
+        assert!(uses_variable("in {{ language }}", "language"));
+        assert!(uses_variable("{{#if language}}x{{/if}}", "language"));
+        assert!(!uses_variable("in {language}", "language"));
+    }
+}
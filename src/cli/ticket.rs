@@ -0,0 +1,206 @@
+use regex::Regex;
+
+use crate::{
+    cli::{diff, repo_config, verbose::print_verbose},
+    constants::{DEFAULT_TICKET_PATTERN, RECENT_COMMITS_COUNT},
+};
+
+/// The config section of the ticket references.
+pub const TICKET_SECTION: &str = "ticket";
+/// The default token of the footer referencing the tickets.
+const DEFAULT_FOOTER_TOKEN: &str = "Refs";
+
+/// Where the ticket ids are added to the commit message.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Placement {
+    /// A footer such as `Refs: PROJ-1234`, the default.
+    Footer,
+    /// Before the subject, such as `PROJ-1234 feat: add login`.
+    Prefix,
+    /// The ticket ids are not added.
+    Off,
+}
+
+/// Finds the ticket ids of the current branch name with the `pattern` of the `[ticket]`
+/// config section, or in the last commit messages referencing one when `from_commits` is set.
+///
+/// # Arguments
+///
+/// * `branch` - The current branch.
+///
+/// # Returns
+///
+/// * `Vec<String>` containing the ticket ids in order of appearance, empty if none is found.
+pub fn find_tickets(branch: &str) -> Vec<String> {
+    let pattern = repo_config::get_config_value(TICKET_SECTION, "pattern")
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| DEFAULT_TICKET_PATTERN.to_string());
+    let regex = match Regex::new(&pattern) {
+        Ok(regex) => regex,
+        Err(e) => {
+            eprintln!("Warning: invalid ticket pattern '{}': {}", pattern, e);
+            return Vec::new();
+        }
+    };
+    let tickets = find_all(branch, &regex);
+    let from_commits = repo_config::get_config_value(TICKET_SECTION, "from_commits")
+        .ok()
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false);
+    if !tickets.is_empty() || !from_commits {
+        return tickets;
+    }
+    // The newest commit referencing a ticket, messages separated by NUL characters
+    let log = diff::git_output(&[
+        "log",
+        "-n",
+        &RECENT_COMMITS_COUNT.to_string(),
+        "--pretty=format:%B%x00",
+    ]);
+    let tickets = log
+        .split('\0')
+        .map(|message| find_all(message, &regex))
+        .find(|tickets| !tickets.is_empty())
+        .unwrap_or_default();
+    print_verbose(&format!("Tickets found in the recent commits: {:?}", tickets));
+    tickets
+}
+
+/// Finds the distinct matches of a ticket pattern in a text, in order of appearance.
+pub fn find_all(text: &str, regex: &Regex) -> Vec<String> {
+    let mut tickets: Vec<String> = Vec::new();
+    for m in regex.find_iter(text) {
+        if !tickets.iter().any(|ticket| ticket == m.as_str()) {
+            tickets.push(m.as_str().to_string());
+        }
+    }
+    tickets
+}
+
+/// Adds the references of the tickets to the commit message, as configured by the
+/// `placement` of the `[ticket]` config section.
+///
+/// # Arguments
+///
+/// * `subject` - The commit subject.
+/// * `message` - The body of the commit message.
+/// * `tickets` - The ticket ids, those already mentioned by the message are not added again.
+///
+/// # Returns
+///
+/// * `(String, String)` containing the subject and the body with the references.
+pub fn add_references(subject: String, message: String, tickets: &[String]) -> (String, String) {
+    let token = repo_config::get_config_value(TICKET_SECTION, "footer_token")
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| DEFAULT_FOOTER_TOKEN.to_string());
+    reference(subject, message, tickets, get_placement(), &token)
+}
+
+fn reference(
+    subject: String,
+    message: String,
+    tickets: &[String],
+    placement: Placement,
+    token: &str,
+) -> (String, String) {
+    let missing = |text: &str| -> Vec<&str> {
+        tickets
+            .iter()
+            .map(String::as_str)
+            .filter(|ticket| !text.contains(ticket))
+            .collect()
+    };
+    match placement {
+        Placement::Footer => {
+            let missing = missing(&format!("{}\n{}", subject, message));
+            if missing.is_empty() {
+                return (subject, message);
+            }
+            let footer = format!("{}: {}", token, missing.join(", "));
+            let message = match message.trim_end() {
+                "" => footer,
+                body => format!("{}\n\n{}", body, footer),
+            };
+            (subject, message)
+        }
+        Placement::Prefix => {
+            let missing = missing(&subject);
+            if missing.is_empty() {
+                return (subject, message);
+            }
+            (format!("{} {}", missing.join(" "), subject), message)
+        }
+        Placement::Off => (subject, message),
+    }
+}
+
+/// Returns the placement of the `[ticket]` config section, `footer` by default.
+fn get_placement() -> Placement {
+    let Ok(value) = repo_config::get_config_value(TICKET_SECTION, "placement") else {
+        return Placement::Footer;
+    };
+    match value.as_str() {
+        Some("footer") => Placement::Footer,
+        Some("prefix") => Placement::Prefix,
+        Some("off") => Placement::Off,
+        _ => {
+            eprintln!(
+                "Warning: unknown ticket placement {}, expected one of: footer, prefix, off",
+                value
+            );
+            Placement::Footer
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Placement, find_all, reference};
+    use crate::constants::DEFAULT_TICKET_PATTERN;
+    use regex::Regex;
+
+    #[test]
+    fn test_find_all() {
+        let regex = Regex::new(DEFAULT_TICKET_PATTERN).unwrap();
+        assert_eq!(find_all("feature/PROJ-1234-login", &regex), vec!["PROJ-1234"]);
+        assert_eq!(
+            find_all("fix/AB-1-and-CD-22-AB-1", &regex),
+            vec!["AB-1", "CD-22"]
+        );
+        assert!(find_all("main", &regex).is_empty());
+        let regex = Regex::new(r"#\d+").unwrap();
+        assert_eq!(find_all("issue/#42-crash", &regex), vec!["#42"]);
+    }
+
+    #[test]
+    fn test_reference() {
+        let tickets = vec!["PROJ-1234".to_string()];
+        let generated = || ("feat: add login".to_string(), "src/a.rs: Add login (3)".to_string());
+        let (subject, message) = generated();
+        assert_eq!(
+            reference(subject, message, &tickets, Placement::Footer, "Refs"),
+            (
+                "feat: add login".to_string(),
+                "src/a.rs: Add login (3)\n\nRefs: PROJ-1234".to_string()
+            )
+        );
+        let (subject, message) = generated();
+        assert_eq!(
+            reference(subject, message, &tickets, Placement::Prefix, "Refs").0,
+            "PROJ-1234 feat: add login"
+        );
+        let (subject, _) = generated();
+        let message = "Closes: PROJ-1234".to_string();
+        assert_eq!(
+            reference(subject, message, &tickets, Placement::Footer, "Refs").1,
+            "Closes: PROJ-1234"
+        );
+        let (subject, message) = generated();
+        assert_eq!(
+            reference(subject, message, &tickets, Placement::Off, "Refs"),
+            generated()
+        );
+    }
+}
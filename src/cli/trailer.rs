@@ -0,0 +1,268 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use gim_config::config;
+use toml::Value;
+
+use crate::cli::{command::GimCli, diff::git_output, repo_config, verbose::print_verbose};
+
+/// The config section of the trailers added to every commit.
+pub const TRAILERS_SECTION: &str = "trailers";
+/// The token of the breaking change footer of Conventional Commits.
+const BREAKING_TOKEN: &str = "BREAKING CHANGE";
+/// The key of the `[trailers]` section adding the sign-off, read from the global config only.
+const SIGNOFF_KEY: &str = "signoff";
+
+/// The trailers and footers added to the commit message.
+#[derive(Debug, Default, PartialEq)]
+pub struct Trailers {
+    /// The trailers, such as `Signed-off-by: Name <email>`, in order.
+    pub trailers: Vec<String>,
+    /// The description of the breaking change.
+    pub breaking: Option<String>,
+}
+
+impl Trailers {
+    /// Collects the trailers of the `[trailers]` config section and of the command line.
+    ///
+    /// When amending, the trailers and the breaking change of the last commit come first so
+    /// that they are kept.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Trailers)` containing the trailers.
+    /// * `Err(String)` if a trailer is invalid or the committer identity is unknown.
+    pub fn collect(cli: &GimCli) -> Result<Self, String> {
+        let mut trailers = Trailers::default();
+        if cli.overwrite {
+            let existing = git_output(&["log", "-1", "--pretty=format:%(trailers:only,unfold)"]);
+            trailers.trailers.extend(
+                existing
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(str::to_string),
+            );
+            let last_message = git_output(&["log", "-1", "--pretty=format:%B"]);
+            trailers.breaking = last_message.lines().find_map(|line| {
+                line.strip_prefix(&format!("{}: ", BREAKING_TOKEN))
+                    .map(str::to_string)
+            });
+        }
+
+        let mut trailers =
+            trailers.add(cli.signoff, &cli.co_author, &cli.reviewed_by, &cli.trailer)?;
+        if let Some(breaking) = &cli.breaking {
+            trailers.breaking = Some(breaking.clone());
+        }
+        print_verbose(&format!("Commit trailers: {:?}", trailers));
+        Ok(trailers)
+    }
+
+    /// Collects the trailers of the `[trailers]` config section alone, as added to the
+    /// messages of the `prepare-commit-msg` hook.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Trailers)` containing the trailers.
+    /// * `Err(String)` if a trailer is invalid or the committer identity is unknown.
+    pub fn from_config() -> Result<Self, String> {
+        let trailers = Trailers::default().add(false, &[], &[], &[])?;
+        print_verbose(&format!("Commit trailers: {:?}", trailers));
+        Ok(trailers)
+    }
+
+    /// Adds the trailers of the `[trailers]` config section, each kind followed by the given
+    /// ones.
+    fn add(
+        mut self,
+        signoff: bool,
+        co_authors: &[String],
+        reviewers: &[String],
+        extra: &[String],
+    ) -> Result<Self, String> {
+        let config = repo_config::get_config()
+            .ok()
+            .and_then(|config| config.get(TRAILERS_SECTION).cloned());
+        let strings = |key: &str| -> Vec<String> {
+            config
+                .as_ref()
+                .and_then(|config| config.get(key))
+                .and_then(Value::as_array)
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        if signoff || signoff_config() {
+            self.trailers
+                .push(format!("Signed-off-by: {}", committer_identity()?));
+        }
+        for author in strings("co_authored_by").iter().chain(co_authors) {
+            self.trailers.push(format!("Co-authored-by: {}", author));
+        }
+        for reviewer in strings("reviewed_by").iter().chain(reviewers) {
+            self.trailers.push(format!("Reviewed-by: {}", reviewer));
+        }
+        for trailer in strings("extra").iter().chain(extra) {
+            check_trailer(trailer)?;
+            self.trailers.push(trailer.clone());
+        }
+        Ok(self)
+    }
+
+    /// Adds the trailers to the commit message with `git interpret-trailers`, the ones already
+    /// in the message are not added again. The breaking change footer is put before them, in
+    /// place of the one already in the message.
+    ///
+    /// # Arguments
+    ///
+    /// * `subject` - The commit subject.
+    /// * `message` - The body of the commit message.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` containing the body with the trailers.
+    /// * `Err(String)` if git failed.
+    pub fn apply(&self, subject: &str, message: &str) -> Result<String, String> {
+        let mut text = format!("{}\n\n{}", subject, message.trim_end())
+            .trim_end()
+            .to_string();
+        if let Some(breaking) = &self.breaking {
+            let footer = format!("{}: {}", BREAKING_TOKEN, breaking);
+            let prefix = format!("{}: ", BREAKING_TOKEN);
+            // The given description wins over the one of the breaking change analysis
+            if let Some(line) = text.lines().find(|line| line.starts_with(&prefix)) {
+                text = text.replacen(line, &footer, 1);
+            }
+            // git takes no token with a space for a trailer, the footer stays out of their block
+            if !text.contains(&footer) {
+                text = match text.rfind("\n\n") {
+                    Some(i) if !interpret_trailers(&["--parse"], &text)?.is_empty() => {
+                        format!("{}\n\n{}{}", &text[..i], footer, &text[i..])
+                    }
+                    _ => format!("{}\n\n{}", text, footer),
+                };
+            }
+        }
+        if !self.trailers.is_empty() {
+            let mut args = vec!["--if-exists", "addIfDifferent"];
+            for trailer in &self.trailers {
+                args.extend(["--trailer", trailer]);
+            }
+            text = interpret_trailers(&args, &text)?;
+        }
+        let body = text.strip_prefix(subject).unwrap_or(&text);
+        Ok(body.trim_start_matches('\n').trim_end().to_string())
+    }
+}
+
+/// Returns `signoff` of the `[trailers]` section of the global config file.
+///
+/// A sign-off is the attestation of the committer, so that a repository config file may not
+/// add it for everyone, as git has no config for `git commit --signoff` either.
+fn signoff_config() -> bool {
+    let repo = repo_config::get_repo_config()
+        .and_then(|(_, config)| config.get(TRAILERS_SECTION)?.get(SIGNOFF_KEY).cloned());
+    if repo.is_some() {
+        eprintln!(
+            "Warning: the signoff of the repository config file is ignored, set it in the global config file or use --signoff"
+        );
+    }
+    config::get_config()
+        .ok()
+        .and_then(|config| config.get(TRAILERS_SECTION)?.get(SIGNOFF_KEY)?.as_bool())
+        .unwrap_or(false)
+}
+
+/// Returns the committer identity as `Name <email>`, as `git commit --signoff` does.
+fn committer_identity() -> Result<String, String> {
+    // Name <email> timestamp timezone
+    let ident = git_output(&["var", "GIT_COMMITTER_IDENT"]);
+    match ident.rsplitn(3, ' ').nth(2) {
+        Some(identity) if identity.contains('<') => Ok(identity.to_string()),
+        _ => Err("the committer identity is unknown, set user.name and user.email with 'git config'".to_string()),
+    }
+}
+
+/// Checks that a trailer is `Token: value`, the token made of letters, digits and dashes.
+fn check_trailer(trailer: &str) -> Result<(), String> {
+    let valid = trailer.split_once(':').is_some_and(|(token, value)| {
+        !token.is_empty()
+            && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && !value.trim().is_empty()
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid trailer '{}', expected 'Token: value'", trailer))
+    }
+}
+
+fn interpret_trailers(args: &[&str], text: &str) -> Result<String, String> {
+    let mut child = Command::new("git")
+        .args(["interpret-trailers", "--no-divider"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run git interpret-trailers: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("failed to run git interpret-trailers: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to run git interpret-trailers: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git interpret-trailers failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Trailers, check_trailer};
+
+    #[test]
+    fn test_check_trailer() {
+        assert!(check_trailer("Acked-by: Jane <jane@example.com>").is_ok());
+        assert!(check_trailer("Acked by: Jane").is_err());
+        assert!(check_trailer("Acked-by:").is_err());
+        assert!(check_trailer("no token").is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let trailers = Trailers {
+            trailers: vec![
+                "Signed-off-by: Jane <jane@example.com>".to_string(),
+                "Refs: PROJ-42".to_string(),
+            ],
+            breaking: Some("the config moved".to_string()),
+        };
+        assert_eq!(
+            trailers.apply("feat: move config", "src/a.rs: Move config (3)\n\nRefs: PROJ-42"),
+            Ok("src/a.rs: Move config (3)\n\nBREAKING CHANGE: the config moved\n\nRefs: PROJ-42\nSigned-off-by: Jane <jane@example.com>".to_string())
+        );
+        assert_eq!(
+            trailers.apply("feat: move config", ""),
+            Ok("BREAKING CHANGE: the config moved\nSigned-off-by: Jane <jane@example.com>\nRefs: PROJ-42".to_string())
+        );
+        assert_eq!(
+            trailers.apply("feat!: move config", "BREAKING CHANGE: removed `pub fn load` from a.rs"),
+            Ok("BREAKING CHANGE: the config moved\nSigned-off-by: Jane <jane@example.com>\nRefs: PROJ-42".to_string())
+        );
+        assert_eq!(Trailers::default().apply("fix: typo", "a.rs: Fix (1)\n"), Ok("a.rs: Fix (1)".to_string()));
+    }
+}
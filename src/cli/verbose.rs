@@ -24,13 +24,14 @@ pub fn is_verbose() -> bool {
     VERBOSE.load(Ordering::Relaxed)
 }
 
-/// Prints a message if verbose mode is enabled.
+/// Prints a message to stderr if verbose mode is enabled, stdout being kept for the output
+/// of the command, such as the message of `--dry-run`.
 ///
 /// # Arguments
 ///
 /// * `message` - The message to print if verbose mode is enabled.
 pub fn print_verbose(message: &str) {
     if is_verbose() {
-        println!("[VERBOSE] {}", message);
+        eprintln!("[VERBOSE] {}", message);
     }
 }
@@ -2,6 +2,8 @@ pub const REPOSITORY: &str = "git-intelligence-message";
 
 pub const DIFF_PROMPT_FILE: &str = "diff_prompt.txt";
 pub const SUBJECT_PROMPT_FILE: &str = "subject_prompt.txt";
+/// The directory of a repository holding its own prompt files.
+pub const REPO_PROMPT_DIR: &str = ".gim";
 
 // Base URLs for different AI providers
 pub const MOONSHOT_BASE_URL: &str = "https://api.moonshot.cn";
@@ -12,14 +14,33 @@ pub const DOUBAO_BASE_URL: &str = "https://ark.cn-beijing.volces.com";
 pub const GLM_BASE_URL: &str = "https://open.bigmodel.cn";
 pub const DEEPSEEK_BASE_URL: &str = "https://api.deepseek.com";
 pub const QIANFAN_BASE_URL: &str = "https://qianfan.baidubce.com";
+pub const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com";
+pub const OLLAMA_BASE_URL: &str = "http://localhost:11434";
 
 // Default paths for different AI providers
 pub const DEFAULT_CHAT_COMPLETIONS_PATH: &str = "/v1/chat/completions";
 pub const QWEN_CHAT_COMPLETIONS_PATH: &str = "/compatible-mode/v1/chat/completions";
-pub const GEMINI_CHAT_COMPLETIONS_PATH: &str = "/v1beta/openai/";
+pub const GEMINI_CHAT_COMPLETIONS_PATH: &str = "/v1beta/openai/chat/completions";
 pub const DOUBAO_CHAT_COMPLETIONS_PATH: &str = "/api/v3/chat/completions";
 pub const GLM_CHAT_COMPLETIONS_PATH: &str = "/api/paas/v4/chat/completions";
 pub const QIANFAN_CHAT_COMPLETIONS_PATH: &str = "/v2/chat/completions";
+pub const ANTHROPIC_MESSAGES_PATH: &str = "/v1/messages";
+pub const OLLAMA_CHAT_PATH: &str = "/api/chat";
 
 pub const CUSTOM_SECTION_NAME: &str = "user";
-pub const DIFF_SIZE_LIMIT: usize = 1000;
+/// The default count of tokens the changes sent to the AI may use.
+pub const DEFAULT_TOKEN_BUDGET: usize = 12000;
+/// The default count of AI requests sent at the same time when summarizing chunks or files.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+/// The default count of times the AI is asked for a subject following Conventional Commits.
+pub const DEFAULT_SUBJECT_ATTEMPTS: usize = 3;
+
+/// Matches ticket ids such as `PROJ-1234` in branch names.
+pub const DEFAULT_TICKET_PATTERN: &str = r"[A-Z][A-Z0-9]+-\d+";
+/// The count of recent commit subjects available to the prompt templates.
+pub const RECENT_COMMITS_COUNT: usize = 5;
+
+// Defaults of the AI request timeouts and retries
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+pub const DEFAULT_READ_TIMEOUT_SECS: u64 = 60;
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
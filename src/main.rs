@@ -22,7 +22,9 @@ async fn main() {
     verbose::set_verbose(cli.verbose);
 
     // Only show update reminder for the main command, not for subcommands
-    if env::args().nth(1).is_none_or(|arg| arg != "update")
+    if env::args()
+        .nth(1)
+        .is_none_or(|arg| arg != "update" && arg != "hook")
         && let Err(e) = check_update_reminder()
     {
         eprintln!("Warning: {}", e)